@@ -0,0 +1,344 @@
+//! Headless analysis of recorded games against the search.
+
+use clap::Args;
+use minimax::{Evaluation, Evaluator, IterativeOptions, ParallelOptions, ParallelSearch, Strategy};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::{Board, Moves, Swipe},
+    evaluators::TwentyFortyEightEvaluator,
+    record::{load_recording, InoutPair},
+};
+
+/// Searches every legal swipe from the board and returns its value from the
+/// perspective of the player, best first.
+pub fn evaluate_moves(
+    search: &mut ParallelSearch<TwentyFortyEightEvaluator>,
+    board: &Board,
+    depth: u8,
+) -> Vec<(Swipe, Evaluation)> {
+    let mut values = Vec::new();
+    for moves in board.get_moves() {
+        let Moves::Player(swipe) = moves else {
+            continue;
+        };
+
+        let mut child = board.clone();
+        child.swipe(swipe);
+
+        // the child is evaluated from the computers perspective
+        let value = if depth <= 1 {
+            -TwentyFortyEightEvaluator.evaluate(&child)
+        } else {
+            search.set_max_depth(depth - 1);
+            search.choose_move(&child);
+            -search.root_value()
+        };
+        values.push((swipe, value));
+    }
+
+    values.sort_by_key(|(_, value)| -(*value as i32));
+    values
+}
+
+pub fn new_search(
+    threads: usize,
+    table_megabytes: usize,
+) -> ParallelSearch<TwentyFortyEightEvaluator> {
+    ParallelSearch::new(
+        TwentyFortyEightEvaluator,
+        IterativeOptions::default().with_table_byte_size(table_megabytes.max(1) * 1_000_000),
+        ParallelOptions::default().with_num_threads(threads.max(1)),
+    )
+}
+
+struct Disagreement {
+    file: String,
+    index: usize,
+    board: Board,
+    human: Swipe,
+    values: Vec<(Swipe, Evaluation)>,
+    loss: i32,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Agreement {
+    positions: usize,
+    agreed: usize,
+}
+
+impl Agreement {
+    fn add(&mut self, agreed: bool) {
+        self.positions += 1;
+        self.agreed += agreed as usize;
+    }
+
+    fn rate(&self) -> f32 {
+        100.0 * self.agreed as f32 / self.positions.max(1) as f32
+    }
+}
+
+/// Reports how often the search agrees with the recorded moves. A recorded
+/// move counts as agreeing when no other swipe has a strictly higher value.
+pub fn agreement_report(
+    files: &[PathBuf],
+    depth: u8,
+    top: usize,
+    search: &mut ParallelSearch<TwentyFortyEightEvaluator>,
+) -> io::Result<()> {
+    let mut total = Agreement::default();
+    let mut by_max_tile = [Agreement::default(); 32];
+    let mut by_phase = [Agreement::default(); 3];
+    let mut disagreements = Vec::new();
+
+    for file in files {
+        let recording = load_recording(&std::fs::read(file)?)?;
+        let mut file_agreement = Agreement::default();
+
+        for (index, pair) in recording.iter().enumerate() {
+            let values = evaluate_moves(search, &pair.input, depth);
+            let Some(&(_, best)) = values.first() else {
+                continue;
+            };
+            let human = values
+                .iter()
+                .find(|(swipe, _)| *swipe == pair.output)
+                .map(|(_, value)| *value)
+                .unwrap_or(best);
+
+            let agreed = human >= best;
+            total.add(agreed);
+            file_agreement.add(agreed);
+
+            let max_tile = pair.input.to_exponents().into_iter().max().unwrap() as usize;
+            by_max_tile[max_tile.min(31)].add(agreed);
+            by_phase[(index * 3 / recording.len()).min(2)].add(agreed);
+
+            if !agreed {
+                disagreements.push(Disagreement {
+                    file: file.display().to_string(),
+                    index,
+                    board: pair.input.clone(),
+                    human: pair.output,
+                    values,
+                    loss: best as i32 - human as i32,
+                });
+            }
+        }
+
+        println!(
+            "{}: {:.1}% agreement over {} positions",
+            file.display(),
+            file_agreement.rate(),
+            file_agreement.positions
+        );
+    }
+
+    println!();
+    println!(
+        "Total: {:.1}% agreement over {} positions at depth {}",
+        total.rate(),
+        total.positions,
+        depth
+    );
+
+    println!();
+    println!("By game phase:");
+    for (name, agreement) in ["opening", "middle", "end"].iter().zip(by_phase) {
+        println!(
+            "  {:>7}: {:>5.1}% ({} positions)",
+            name,
+            agreement.rate(),
+            agreement.positions
+        );
+    }
+
+    println!();
+    println!("By max tile:");
+    for (exp, agreement) in by_max_tile.iter().enumerate() {
+        if agreement.positions > 0 {
+            println!(
+                "  {:>7}: {:>5.1}% ({} positions)",
+                1u32 << exp,
+                agreement.rate(),
+                agreement.positions
+            );
+        }
+    }
+
+    disagreements.sort_by_key(|disagreement| -disagreement.loss);
+    println!();
+    println!("Biggest disagreements:");
+    for disagreement in disagreements.iter().take(top) {
+        println!(
+            "{} #{}: played {}, loses {}",
+            disagreement.file, disagreement.index, disagreement.human, disagreement.loss
+        );
+        for (swipe, value) in &disagreement.values {
+            println!("  {:>5}: {}", swipe, value);
+        }
+        println!("{}", disagreement.board);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationKind {
+    /// The recorded swipe was much worse than the best one
+    Blunder,
+    /// Only one swipe avoids losing within a few moves
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Annotation {
+    pub index: usize,
+    pub kind: AnnotationKind,
+    /// How much worse the recorded swipe was than the best one
+    pub loss: i32,
+}
+
+pub struct ReviewOptions {
+    pub depth: u8,
+    pub blunder_threshold: i32,
+    pub quick_loss_moves: u8,
+    pub threads: usize,
+    pub table_megabytes: usize,
+}
+
+impl Default for ReviewOptions {
+    fn default() -> Self {
+        Self {
+            depth: 6,
+            blunder_threshold: 500,
+            quick_loss_moves: 3,
+            threads: 8,
+            table_megabytes: 128,
+        }
+    }
+}
+
+/// Runs the search on every recorded position and annotates blunders and
+/// critical positions
+pub fn review(recording: &[InoutPair<Board, Swipe>], options: &ReviewOptions) -> Vec<Annotation> {
+    let mut search = new_search(options.threads, options.table_megabytes);
+    let mut annotations = Vec::new();
+
+    for (index, pair) in recording.iter().enumerate() {
+        let values = evaluate_moves(&mut search, &pair.input, options.depth);
+        let Some(&(_, best)) = values.first() else {
+            continue;
+        };
+        let played = values
+            .iter()
+            .find(|(swipe, _)| *swipe == pair.output)
+            .map(|(_, value)| *value)
+            .unwrap_or(best);
+
+        let loss = best as i32 - played as i32;
+        if loss > options.blunder_threshold {
+            annotations.push(Annotation {
+                index,
+                kind: AnnotationKind::Blunder,
+                loss,
+            });
+        }
+
+        let surviving = values
+            .iter()
+            .filter(|(swipe, _)| {
+                let mut child = pair.input.clone();
+                child.swipe(*swipe);
+                survives(&child, options.quick_loss_moves)
+            })
+            .count();
+        if values.len() > 1 && surviving == 1 {
+            annotations.push(Annotation {
+                index,
+                kind: AnnotationKind::Critical,
+                loss,
+            });
+        }
+    }
+
+    annotations
+}
+
+/// Returns true if the player can keep moving for the given number of swipes
+/// no matter where the tiles are placed
+fn survives(board: &Board, moves: u8) -> bool {
+    if board.player_to_move && moves == 0 {
+        return true;
+    }
+
+    let mut children = board.get_moves().into_iter().map(|moves| {
+        let mut child = board.clone();
+        child.apply_move(moves);
+        child
+    });
+    match board.player_to_move {
+        true => children.any(|child| survives(&child, moves - 1)),
+        false => children.all(|child| survives(&child, moves)),
+    }
+}
+
+/// The annotation file stored alongside a recording
+pub fn annotations_path(recording: &Path) -> PathBuf {
+    recording.with_extension("review")
+}
+
+pub fn save_annotations(path: &Path, annotations: &[Annotation]) -> io::Result<()> {
+    let mut output = String::new();
+    for annotation in annotations {
+        let kind = match annotation.kind {
+            AnnotationKind::Blunder => "blunder",
+            AnnotationKind::Critical => "critical",
+        };
+        output.push_str(&format!(
+            "{} {} {}\n",
+            annotation.index, kind, annotation.loss
+        ));
+    }
+    std::fs::write(path, output)
+}
+
+pub fn load_annotations(path: &Path) -> io::Result<Vec<Annotation>> {
+    let input = std::fs::read_to_string(path)?;
+
+    let mut annotations = Vec::new();
+    for line in input.lines() {
+        let mut parts = line.split_whitespace();
+        let index = parts.next().and_then(|index| index.parse().ok());
+        let kind = match parts.next() {
+            Some("blunder") => Some(AnnotationKind::Blunder),
+            Some("critical") => Some(AnnotationKind::Critical),
+            _ => None,
+        };
+        let loss = parts.next().and_then(|loss| loss.parse().ok());
+
+        if let (Some(index), Some(kind), Some(loss)) = (index, kind, loss) {
+            annotations.push(Annotation { index, kind, loss });
+        }
+    }
+    Ok(annotations)
+}
+
+/// Options for the `analyze` subcommand
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// Recordings to compare against the search
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// How many of the worst disagreements to list
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+pub fn run(args: AnalyzeArgs, depth: u8, threads: usize, table_megabytes: usize) -> io::Result<()> {
+    let mut search = new_search(threads, table_megabytes);
+    agreement_report(&args.files, depth, args.top, &mut search)
+}
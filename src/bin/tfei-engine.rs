@@ -0,0 +1,27 @@
+//! The reference tfei engine on its own, for testing engine players without
+//! the game window. It plays the same as the `engine` subcommand.
+
+use clap::Parser;
+use twenty_forty_eight::{cli::SearchArgs, engine};
+
+#[derive(Parser)]
+#[command(version, about = "The reference tfei engine, over stdin and stdout")]
+struct Cli {
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+fn main() {
+    let Cli { search } = Cli::parse();
+    let result = engine::serve(
+        search.strategy(),
+        search.depth.unwrap_or(8),
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+    );
+
+    if let Err(error) = result {
+        eprintln!("Engine failed: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,461 @@
+//! Remappable controls. Keys, shortcuts with a modifier held, gamepad buttons
+//! and the left stick are bound to actions, which are turned into
+//! [`GameCommand`]s. The bindings are saved with the rest of the config.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use serde::{Deserialize, Serialize};
+
+use crate::{board::Swipe, ui::UiSettings, GameCommand};
+
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Bindings>()
+            .init_resource::<Rebinding>()
+            .init_resource::<CheatSheet>()
+            .add_systems(
+                Update,
+                (
+                    read_bindings.before(crate::apply_commands),
+                    bindings_ui,
+                    cheat_sheet_ui,
+                ),
+            );
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    SwipeUp,
+    SwipeDown,
+    SwipeLeft,
+    SwipeRight,
+    Undo,
+    Reset,
+    ToggleAutoplay,
+    DepthUp,
+    DepthDown,
+    ReplayBack,
+    ReplayForward,
+    ToggleRecording,
+    LoadRecording,
+    CopyPosition,
+    PastePosition,
+    EditPosition,
+    ToggleCheatSheet,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::SwipeUp,
+        Action::SwipeDown,
+        Action::SwipeLeft,
+        Action::SwipeRight,
+        Action::Undo,
+        Action::Reset,
+        Action::ToggleAutoplay,
+        Action::DepthUp,
+        Action::DepthDown,
+        Action::ReplayBack,
+        Action::ReplayForward,
+        Action::ToggleRecording,
+        Action::LoadRecording,
+        Action::CopyPosition,
+        Action::PastePosition,
+        Action::EditPosition,
+        Action::ToggleCheatSheet,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::SwipeUp => "Swipe up",
+            Action::SwipeDown => "Swipe down",
+            Action::SwipeLeft => "Swipe left",
+            Action::SwipeRight => "Swipe right",
+            Action::Undo => "Undo",
+            Action::Reset => "New game",
+            Action::ToggleAutoplay => "Toggle autoplay",
+            Action::DepthUp => "Search deeper",
+            Action::DepthDown => "Search shallower",
+            Action::ReplayBack => "Replay back",
+            Action::ReplayForward => "Replay forward",
+            Action::ToggleRecording => "Start or stop recording",
+            Action::LoadRecording => "Load recording",
+            Action::CopyPosition => "Copy position",
+            Action::PastePosition => "Paste position",
+            Action::EditPosition => "Edit position",
+            Action::ToggleCheatSheet => "Show or hide shortcuts",
+        }
+    }
+
+    /// The command sent for this action, if it isn't handled here
+    fn command(&self, ui_settings: &UiSettings) -> Option<GameCommand> {
+        Some(match self {
+            Action::SwipeUp => GameCommand::Swipe(Swipe::Up),
+            Action::SwipeDown => GameCommand::Swipe(Swipe::Down),
+            Action::SwipeLeft => GameCommand::Swipe(Swipe::Left),
+            Action::SwipeRight => GameCommand::Swipe(Swipe::Right),
+            Action::Undo => GameCommand::Undo,
+            Action::Reset => GameCommand::Reset { seed: None },
+            Action::ToggleAutoplay => GameCommand::SetAutomatic(!ui_settings.automatic),
+            Action::DepthUp => GameCommand::SetDepth(ui_settings.depth.saturating_add(1)),
+            Action::DepthDown => GameCommand::SetDepth(ui_settings.depth.max(2) - 1),
+            Action::ReplayBack => GameCommand::StepReplay(-1),
+            Action::ReplayForward => GameCommand::StepReplay(1),
+            Action::ToggleRecording => GameCommand::ToggleRecording,
+            Action::LoadRecording => GameCommand::LoadRecording,
+            Action::CopyPosition => GameCommand::CopyPosition,
+            Action::PastePosition => GameCommand::PastePosition,
+            Action::EditPosition => GameCommand::EditPosition,
+            Action::ToggleCheatSheet => return None,
+        })
+    }
+}
+
+/// A direction the left stick is pushed in, past halfway
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StickDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl StickDirection {
+    fn from_axes(x: f32, y: f32) -> Option<Self> {
+        const THRESHOLD: f32 = 0.5;
+        if x.abs().max(y.abs()) < THRESHOLD {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x > 0.0 {
+                StickDirection::Right
+            } else {
+                StickDirection::Left
+            })
+        } else if y > 0.0 {
+            Some(StickDirection::Up)
+        } else {
+            Some(StickDirection::Down)
+        }
+    }
+}
+
+/// A key held down for a shortcut. Either the left or right one counts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Ctrl,
+    Alt,
+    Shift,
+}
+
+impl Modifier {
+    const ALL: [Modifier; 3] = [Modifier::Ctrl, Modifier::Alt, Modifier::Shift];
+
+    fn keys(&self) -> [KeyCode; 2] {
+        match self {
+            Modifier::Ctrl => [KeyCode::ControlLeft, KeyCode::ControlRight],
+            Modifier::Alt => [KeyCode::AltLeft, KeyCode::AltRight],
+            Modifier::Shift => [KeyCode::ShiftLeft, KeyCode::ShiftRight],
+        }
+    }
+}
+
+/// An input that can trigger an action. Any connected gamepad can be used.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// A key pressed with no modifier held
+    Key(KeyCode),
+    /// A key pressed with a modifier held. When several are held Ctrl wins
+    /// over Alt, and Alt over Shift.
+    Shortcut(Modifier, KeyCode),
+    /// A typed punctuation character, whichever keys it takes on the keyboard
+    /// layout
+    Char(char),
+    Gamepad(GamepadButtonType),
+    Stick(StickDirection),
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Shortcut(modifier, key) => write!(f, "{:?}+{:?}", modifier, key),
+            Binding::Char(c) => write!(f, "{}", c),
+            Binding::Gamepad(button) => write!(f, "Pad {:?}", button),
+            Binding::Stick(direction) => write!(f, "Stick {:?}", direction),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BindingEntry {
+    pub action: Action,
+    pub input: Binding,
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Bindings(pub Vec<BindingEntry>);
+
+/// The version of the default bindings. Defaults added since a config was
+/// saved are merged into its bindings when it's loaded.
+pub const BINDINGS_VERSION: u32 = 2;
+
+/// Every default binding with the version it was added in
+fn default_bindings() -> Vec<(u32, Action, Binding)> {
+    use Binding::{Char, Gamepad, Key, Shortcut, Stick};
+    use GamepadButtonType as Pad;
+
+    vec![
+        (1, Action::SwipeUp, Key(KeyCode::Up)),
+        (1, Action::SwipeUp, Key(KeyCode::W)),
+        (1, Action::SwipeUp, Gamepad(Pad::DPadUp)),
+        (1, Action::SwipeUp, Stick(StickDirection::Up)),
+        (1, Action::SwipeDown, Key(KeyCode::Down)),
+        (1, Action::SwipeDown, Key(KeyCode::S)),
+        (1, Action::SwipeDown, Gamepad(Pad::DPadDown)),
+        (1, Action::SwipeDown, Stick(StickDirection::Down)),
+        (1, Action::SwipeLeft, Key(KeyCode::Left)),
+        (1, Action::SwipeLeft, Key(KeyCode::A)),
+        (1, Action::SwipeLeft, Gamepad(Pad::DPadLeft)),
+        (1, Action::SwipeLeft, Stick(StickDirection::Left)),
+        (1, Action::SwipeRight, Key(KeyCode::Right)),
+        (1, Action::SwipeRight, Key(KeyCode::D)),
+        (1, Action::SwipeRight, Gamepad(Pad::DPadRight)),
+        (1, Action::SwipeRight, Stick(StickDirection::Right)),
+        (1, Action::Undo, Key(KeyCode::U)),
+        (1, Action::Undo, Gamepad(Pad::West)),
+        (2, Action::Reset, Key(KeyCode::R)),
+        (1, Action::Reset, Gamepad(Pad::Select)),
+        (2, Action::ToggleAutoplay, Key(KeyCode::Space)),
+        (1, Action::ToggleAutoplay, Gamepad(Pad::North)),
+        (2, Action::DepthUp, Key(KeyCode::BracketRight)),
+        (1, Action::DepthUp, Gamepad(Pad::RightTrigger2)),
+        (2, Action::DepthDown, Key(KeyCode::BracketLeft)),
+        (1, Action::DepthDown, Gamepad(Pad::LeftTrigger2)),
+        (1, Action::ReplayBack, Key(KeyCode::Comma)),
+        (1, Action::ReplayBack, Gamepad(Pad::LeftTrigger)),
+        (1, Action::ReplayForward, Key(KeyCode::Period)),
+        (1, Action::ReplayForward, Gamepad(Pad::RightTrigger)),
+        (
+            2,
+            Action::ToggleRecording,
+            Shortcut(Modifier::Ctrl, KeyCode::R),
+        ),
+        (
+            2,
+            Action::LoadRecording,
+            Shortcut(Modifier::Ctrl, KeyCode::O),
+        ),
+        (
+            2,
+            Action::CopyPosition,
+            Shortcut(Modifier::Ctrl, KeyCode::C),
+        ),
+        (
+            2,
+            Action::PastePosition,
+            Shortcut(Modifier::Ctrl, KeyCode::V),
+        ),
+        (2, Action::EditPosition, Key(KeyCode::E)),
+        (2, Action::ToggleCheatSheet, Char('?')),
+        (2, Action::ToggleCheatSheet, Gamepad(Pad::Start)),
+    ]
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self(
+            default_bindings()
+                .into_iter()
+                .map(|(_, action, input)| BindingEntry { action, input })
+                .collect(),
+        )
+    }
+}
+
+impl Bindings {
+    /// Adds the defaults from after `version` whose inputs aren't bound yet
+    pub fn upgrade(&mut self, version: u32) {
+        for (added, action, input) in default_bindings() {
+            if added > version && self.actions(input).next().is_none() {
+                self.0.push(BindingEntry { action, input });
+            }
+        }
+    }
+
+    pub fn inputs(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+        self.0
+            .iter()
+            .filter(move |entry| entry.action == action)
+            .map(|entry| entry.input)
+    }
+
+    pub fn actions(&self, input: Binding) -> impl Iterator<Item = Action> + '_ {
+        self.0
+            .iter()
+            .filter(move |entry| entry.input == input)
+            .map(|entry| entry.action)
+    }
+}
+
+/// The action waiting for an input to be bound to it
+#[derive(Resource, Default)]
+struct Rebinding(Option<Action>);
+
+/// Whether the overlay listing every binding is shown
+#[derive(Resource, Default)]
+struct CheatSheet(bool);
+
+/// Sends the commands for the actions whose inputs were just pressed, or
+/// binds the first input pressed while rebinding
+#[allow(clippy::too_many_arguments)]
+fn read_bindings(
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    mut stick: Local<Option<StickDirection>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut contexts: EguiContexts,
+    ui_settings: Res<UiSettings>,
+    mut cheat_sheet: ResMut<CheatSheet>,
+    mut game_commands: EventWriter<GameCommand>,
+) {
+    let mut pressed = Vec::new();
+
+    let typed: Vec<char> = characters.iter().map(|character| character.char).collect();
+    // keys typed into the settings window aren't meant for the game
+    if !contexts.ctx_mut().wants_keyboard_input() {
+        // before the keys, so rebinding picks the character over the keys
+        // that typed it
+        pressed.extend(
+            typed
+                .into_iter()
+                .filter(char::is_ascii_punctuation)
+                .map(Binding::Char),
+        );
+        let modifier = Modifier::ALL
+            .into_iter()
+            .find(|modifier| keys.any_pressed(modifier.keys()));
+        // modifiers are only bound together with another key
+        let modifier_keys: Vec<KeyCode> = Modifier::ALL.iter().flat_map(Modifier::keys).collect();
+        pressed.extend(
+            keys.get_just_pressed()
+                .filter(|key| !modifier_keys.contains(key))
+                .map(|&key| match modifier {
+                    Some(modifier) => Binding::Shortcut(modifier, key),
+                    None => Binding::Key(key),
+                }),
+        );
+    }
+    pressed.extend(
+        buttons
+            .get_just_pressed()
+            .map(|button| Binding::Gamepad(button.button_type)),
+    );
+
+    // the stick counts as pressed when it's pushed into a new direction
+    let direction = gamepads.iter().find_map(|gamepad| {
+        let x = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX));
+        let y = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY));
+        StickDirection::from_axes(x.unwrap_or(0.0), y.unwrap_or(0.0))
+    });
+    if direction != *stick {
+        pressed.extend(direction.map(Binding::Stick));
+        *stick = direction;
+    }
+
+    if let Some(action) = rebinding.0 {
+        if let Some(&input) = pressed.first() {
+            if input != Binding::Key(KeyCode::Escape)
+                && bindings.actions(input).all(|a| a != action)
+            {
+                bindings.0.push(BindingEntry { action, input });
+            }
+            rebinding.0 = None;
+        }
+        return;
+    }
+
+    for input in pressed {
+        for action in bindings.actions(input) {
+            match action.command(&ui_settings) {
+                Some(command) => game_commands.send(command),
+                None => cheat_sheet.0 = !cheat_sheet.0,
+            }
+        }
+    }
+}
+
+fn bindings_ui(
+    mut contexts: EguiContexts,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    egui::Window::new("Controls")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let mut removed = None;
+            egui::Grid::new("bindings").striped(true).show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.name());
+                    ui.horizontal_wrapped(|ui| {
+                        for input in bindings.inputs(action) {
+                            let button = ui.small_button(input.to_string());
+                            if button.on_hover_text("Click to remove").clicked() {
+                                removed = Some(BindingEntry { action, input });
+                            }
+                        }
+                    });
+                    if rebinding.0 == Some(action) {
+                        ui.label("Press an input, or Escape to cancel");
+                    } else if ui.button("Add").clicked() {
+                        rebinding.0 = Some(action);
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if let Some(removed) = removed {
+                bindings.0.retain(|entry| *entry != removed);
+            }
+            if ui.button("Reset to defaults").clicked() {
+                *bindings = Bindings::default();
+            }
+        });
+}
+
+/// Every action with its bindings, over the middle of the board
+fn cheat_sheet_ui(
+    mut contexts: EguiContexts,
+    bindings: Res<Bindings>,
+    mut cheat_sheet: ResMut<CheatSheet>,
+) {
+    if !cheat_sheet.0 {
+        return;
+    }
+
+    egui::Window::new("Shortcuts")
+        .open(&mut cheat_sheet.0)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("cheat_sheet").striped(true).show(ui, |ui| {
+                for action in Action::ALL {
+                    let inputs: Vec<_> = bindings.inputs(action).map(|b| b.to_string()).collect();
+                    if inputs.is_empty() {
+                        continue;
+                    }
+                    ui.label(action.name());
+                    ui.strong(inputs.join(", "));
+                    ui.end_row();
+                }
+            });
+        });
+}
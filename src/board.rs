@@ -1,11 +1,21 @@
 use rand::Rng;
 
+/// The largest tile exponent a position can hold, 2^17 = 131072, the largest
+/// tile a 4x4 board can reach
+pub const MAX_EXPONENT: u8 = 17;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Board {
     pub data: Vec<Vec<u8>>,
     pub player_to_move: bool,
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Board {
     pub fn new() -> Self {
         Board {
@@ -57,8 +67,8 @@ impl Board {
         changed
     }
 
-    pub fn computer_move(&mut self) {
-        self.add_random();
+    pub fn computer_move_with(&mut self, rng: &mut impl Rng) {
+        self.add_random_with(rng);
         self.player_to_move = true;
     }
 
@@ -66,7 +76,7 @@ impl Board {
         match self.player_to_move {
             true => {
                 let mut moves = Vec::new();
-                for swipe in [Swipe::Up, Swipe::Down, Swipe::Left, Swipe::Right].iter() {
+                for swipe in Swipe::ALL.iter() {
                     let mut board = self.clone();
                     if board.swipe(*swipe) {
                         moves.push(Moves::Player(*swipe));
@@ -88,6 +98,19 @@ impl Board {
         }
     }
 
+    /// Whether any swipe would change the board, without trying each one.
+    /// That's when some tile is next to an empty square or an equal tile.
+    pub fn can_swipe(&self) -> bool {
+        let movable = |a: u8, b: u8| (a == 0) != (b == 0) || (a != 0 && a == b);
+        (0..4).any(|y| {
+            (0..4).any(|x| {
+                let tile = self.data[y][x];
+                (x < 3 && movable(tile, self.data[y][x + 1]))
+                    || (y < 3 && movable(tile, self.data[y + 1][x]))
+            })
+        })
+    }
+
     pub fn apply_move(&mut self, moves: Moves) {
         match moves {
             Moves::Player(swipe) => {
@@ -120,7 +143,49 @@ impl Board {
         score
     }
 
-    pub fn add_random(&mut self) {
+    /// The value of the largest tile, or 0 on an empty board
+    pub fn max_tile(&self) -> u32 {
+        let max = self.to_exponents().into_iter().max().unwrap_or(0);
+        if max == 0 {
+            0
+        } else {
+            1 << max
+        }
+    }
+
+    /// The tile exponents in row-major order
+    pub fn to_exponents(&self) -> [u8; 16] {
+        let mut exponents = [0; 16];
+        for y in 0..4 {
+            for x in 0..4 {
+                exponents[y * 4 + x] = self.data[y][x];
+            }
+        }
+        exponents
+    }
+
+    pub fn from_exponents(exponents: [u8; 16]) -> Self {
+        let mut board = Board::new();
+        for y in 0..4 {
+            for x in 0..4 {
+                board.data[y][x] = exponents[y * 4 + x];
+            }
+        }
+        board
+    }
+
+    pub fn transformed(&self, symmetry: Symmetry) -> Self {
+        let mut board = self.clone();
+        for y in 0..4 {
+            for x in 0..4 {
+                let pos = symmetry.apply(Pos::new(x, y));
+                board.data[pos.y][pos.x] = self.data[y][x];
+            }
+        }
+        board
+    }
+
+    pub fn add_random_with(&mut self, rng: &mut impl Rng) {
         let mut empty_tiles = Vec::new();
         for y in 0..4 {
             for x in 0..4 {
@@ -131,7 +196,6 @@ impl Board {
         }
 
         if empty_tiles.len() > 0 {
-            let mut rng = rand::thread_rng();
             let index = rng.gen_range(0..empty_tiles.len());
             let (x, y) = empty_tiles[index];
 
@@ -165,6 +229,38 @@ pub enum Swipe {
 }
 
 impl Swipe {
+    pub const ALL: [Swipe; 4] = [Swipe::Up, Swipe::Down, Swipe::Left, Swipe::Right];
+
+    /// Index of the swipe, matching the encoding used in recordings
+    pub fn index(&self) -> usize {
+        match self {
+            Swipe::Up => 0,
+            Swipe::Down => 1,
+            Swipe::Left => 2,
+            Swipe::Right => 3,
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<Swipe> {
+        Swipe::ALL.get(index).copied()
+    }
+
+    /// The swipe that does the same thing on a board transformed by the symmetry
+    pub fn transformed(&self, symmetry: Symmetry) -> Swipe {
+        let (dx, dy) = match self {
+            Swipe::Up => (0, -1),
+            Swipe::Down => (0, 1),
+            Swipe::Left => (-1, 0),
+            Swipe::Right => (1, 0),
+        };
+        match symmetry.apply_direction(dx, dy) {
+            (0, -1) => Swipe::Up,
+            (0, 1) => Swipe::Down,
+            (-1, 0) => Swipe::Left,
+            _ => Swipe::Right,
+        }
+    }
+
     fn abtoxy(&self, a: usize, b: usize) -> Pos {
         match self {
             Swipe::Up => Pos::new(a, b),
@@ -175,6 +271,59 @@ impl Swipe {
     }
 }
 
+/// The 8 symmetries of the square board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    pub fn apply(&self, pos: Pos) -> Pos {
+        let (x, y) = (pos.x, pos.y);
+        match self {
+            Symmetry::Identity => Pos::new(x, y),
+            Symmetry::Rotate90 => Pos::new(3 - y, x),
+            Symmetry::Rotate180 => Pos::new(3 - x, 3 - y),
+            Symmetry::Rotate270 => Pos::new(y, 3 - x),
+            Symmetry::FlipHorizontal => Pos::new(3 - x, y),
+            Symmetry::FlipVertical => Pos::new(x, 3 - y),
+            Symmetry::Transpose => Pos::new(y, x),
+            Symmetry::AntiTranspose => Pos::new(3 - y, 3 - x),
+        }
+    }
+
+    fn apply_direction(&self, dx: i32, dy: i32) -> (i32, i32) {
+        match self {
+            Symmetry::Identity => (dx, dy),
+            Symmetry::Rotate90 => (-dy, dx),
+            Symmetry::Rotate180 => (-dx, -dy),
+            Symmetry::Rotate270 => (dy, -dx),
+            Symmetry::FlipHorizontal => (-dx, dy),
+            Symmetry::FlipVertical => (dx, -dy),
+            Symmetry::Transpose => (dy, dx),
+            Symmetry::AntiTranspose => (-dy, -dx),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ComputerMoves {
     PlaceTwo(Pos),
@@ -205,3 +354,49 @@ impl std::fmt::Display for Board {
         write!(f, "{}", board)
     }
 }
+
+impl std::fmt::Display for Swipe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Swipe::Up => "up",
+            Swipe::Down => "down",
+            Swipe::Left => "left",
+            Swipe::Right => "right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Swipe {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "up" => Ok(Swipe::Up),
+            "down" => Ok(Swipe::Down),
+            "left" => Ok(Swipe::Left),
+            "right" => Ok(Swipe::Right),
+            _ => Err(format!("Invalid direction: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_swipe_matches_moves() {
+        let boards = [
+            [0; 16],
+            [1, 2, 1, 2, 2, 1, 2, 1, 1, 2, 1, 2, 2, 1, 2, 1],
+            [1, 2, 1, 2, 2, 1, 2, 1, 1, 2, 1, 2, 2, 1, 2, 2],
+            [1, 2, 1, 2, 2, 1, 2, 1, 1, 2, 1, 2, 2, 1, 2, 0],
+            [1, 2, 1, 2, 2, 1, 2, 1, 1, 2, 1, 2, 1, 3, 4, 5],
+        ];
+        for exponents in boards {
+            let board = Board::from_exponents(exponents);
+            assert_eq!(board.can_swipe(), !board.get_moves().is_empty());
+        }
+    }
+}
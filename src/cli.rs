@@ -0,0 +1,93 @@
+//! The command line interface. Without a subcommand the game window opens.
+
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::{
+    analysis::AnalyzeArgs,
+    dataset::DatasetArgs,
+    player::{SimulateArgs, TournamentArgs},
+    policy::TrainArgs,
+    strategy::Strategy,
+};
+
+#[derive(Parser)]
+#[command(version, about = "2048 with search, analysis and training tools")]
+pub struct Cli {
+    #[command(flatten)]
+    pub search: SearchArgs,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Flags shared by every subcommand. Anything left out falls back to the
+/// subcommand's own default.
+#[derive(Args, Clone, Default)]
+pub struct SearchArgs {
+    /// Seed for the computer's tile placements
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+    /// Search depth in plies
+    #[arg(long, global = true)]
+    pub depth: Option<u8>,
+    /// One of negamax, parallel, mcts, expectimax or random
+    #[arg(long, global = true)]
+    pub strategy: Option<Strategy>,
+    /// Threads for the parallel search and MCTS
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+    /// Transposition table size for the parallel search, in megabytes
+    #[arg(long, global = true, value_name = "MEGABYTES")]
+    pub table_size: Option<usize>,
+}
+
+impl SearchArgs {
+    /// The chosen strategy with the thread and table size flags applied
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+            .clone()
+            .unwrap_or_default()
+            .with_resources(self.threads, self.table_size)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or(8)
+    }
+
+    pub fn table_size(&self) -> usize {
+        self.table_size.unwrap_or(128)
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Open the game window
+    Gui {
+        /// Serve the local HTTP API, on port 2048 unless another is given
+        #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "2048")]
+        http: Option<u16>,
+    },
+    /// Play games without a window and print the results
+    Simulate(SimulateArgs),
+    /// Print the positions and swipes of a recording
+    Replay {
+        file: PathBuf,
+        /// Milliseconds to wait between moves
+        #[arg(long, default_value_t = 0)]
+        delay: u64,
+    },
+    /// Convert a recording between .tfer, .jsonl, .csv and .npy
+    Convert { input: PathBuf, output: PathBuf },
+    /// Report how often the search agrees with recorded moves
+    Analyze(AnalyzeArgs),
+    /// Play in the terminal
+    Tui,
+    /// Build a training dataset from recordings
+    Dataset(DatasetArgs),
+    /// Train a policy from recordings
+    Train(TrainArgs),
+    /// Compare players over the same seeded games
+    Tournament(TournamentArgs),
+    /// Run the reference engine over stdin and stdout
+    Engine,
+}
@@ -0,0 +1,213 @@
+//! Settings and the last game, kept in the user's config directory between
+//! launches. Settings are written whenever they change in the app, and the
+//! game is saved on exit if `resume_last_game` is on.
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+use crate::{
+    bindings::{Bindings, BINDINGS_VERSION},
+    cli::SearchArgs,
+    record::GameMetadata,
+    slots::SavedGame,
+    ui::UiSettings,
+    BoardResource, GameRng,
+};
+
+pub struct ConfigPlugin {
+    /// The settings the app was launched with
+    pub config: Config,
+}
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SavedConfig(self.config.clone()))
+            .add_systems(Update, save_config)
+            .add_systems(Last, save_session);
+    }
+}
+
+/// The directory all the app's files are kept in
+pub fn config_dir() -> io::Result<PathBuf> {
+    dirs::config_dir()
+        .map(|dir| dir.join("twenty-forty-eight"))
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "No config directory"))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub depth: u8,
+    /// Milliseconds between automatic moves
+    pub speed: f32,
+    pub automatic: bool,
+    /// One of negamax, parallel, mcts, expectimax or random
+    pub strategy: String,
+    pub threads: usize,
+    /// Transposition table size in megabytes
+    pub table_size: usize,
+    pub resume_last_game: bool,
+    /// Pixels a drag has to cover to count as a swipe
+    pub swipe_distance: f32,
+    /// How much longer than its width a drag has to be
+    pub swipe_ratio: f32,
+    /// The name of a built in theme or one in the themes folder
+    pub theme: String,
+    /// The defaults the bindings were saved with, so newer ones can be added.
+    /// Configs from before it was saved have the first version.
+    #[serde(default = "first_bindings_version")]
+    pub bindings_version: u32,
+    pub bindings: Bindings,
+}
+
+fn first_bindings_version() -> u32 {
+    1
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            depth: 8,
+            speed: 0.0,
+            automatic: false,
+            strategy: "parallel".to_string(),
+            threads: 8,
+            table_size: 128,
+            resume_last_game: false,
+            swipe_distance: 30.0,
+            swipe_ratio: 1.5,
+            theme: "Classic".to_string(),
+            bindings_version: BINDINGS_VERSION,
+            bindings: Bindings::default(),
+        }
+    }
+}
+
+impl Config {
+    fn path() -> io::Result<PathBuf> {
+        Ok(config_dir()?.join("config.toml"))
+    }
+
+    /// Loads the saved settings, or the defaults if none have been saved yet
+    pub fn load() -> io::Result<Self> {
+        let input = match std::fs::read_to_string(Self::path()?) {
+            Ok(input) => input,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+        let mut config: Self = toml::from_str(&input)
+            .map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+        config.bindings.upgrade(config.bindings_version);
+        config.bindings_version = BINDINGS_VERSION;
+        Ok(config)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let output =
+            toml::to_string(self).map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+        std::fs::create_dir_all(config_dir()?)?;
+        std::fs::write(Self::path()?, output)
+    }
+
+    /// The settings to start with, where command line flags take precedence
+    /// over the saved ones
+    pub fn settings(&self, search: &SearchArgs) -> UiSettings {
+        let strategy = search
+            .strategy
+            .clone()
+            .or_else(|| self.strategy.parse().ok())
+            .unwrap_or_default()
+            .with_resources(
+                Some(search.threads.unwrap_or(self.threads)),
+                Some(search.table_size.unwrap_or(self.table_size)),
+            );
+
+        UiSettings {
+            strategy,
+            depth: search.depth.unwrap_or(self.depth),
+            automatic: self.automatic,
+            speed: self.speed,
+            resume_last_game: self.resume_last_game,
+            swipe_distance: self.swipe_distance,
+            swipe_ratio: self.swipe_ratio,
+            theme: self.theme.clone(),
+            ..default()
+        }
+    }
+
+    /// This config updated to match the settings. The thread count and table
+    /// size are kept when the strategy doesn't use them.
+    pub fn with_settings(&self, settings: &UiSettings, bindings: &Bindings) -> Self {
+        let strategy = &settings.strategy;
+        Self {
+            depth: settings.depth,
+            speed: settings.speed,
+            automatic: settings.automatic,
+            strategy: strategy.name().to_string(),
+            threads: strategy.threads().unwrap_or(self.threads),
+            table_size: strategy.table_megabytes().unwrap_or(self.table_size),
+            resume_last_game: settings.resume_last_game,
+            swipe_distance: settings.swipe_distance,
+            swipe_ratio: settings.swipe_ratio,
+            theme: settings.theme.clone(),
+            bindings_version: BINDINGS_VERSION,
+            bindings: bindings.clone(),
+        }
+    }
+}
+
+/// The config as it was last loaded or saved
+#[derive(Resource)]
+struct SavedConfig(Config);
+
+fn save_config(
+    ui_settings: Res<UiSettings>,
+    bindings: Res<Bindings>,
+    mut saved: ResMut<SavedConfig>,
+) {
+    if !ui_settings.is_changed() && !bindings.is_changed() {
+        return;
+    }
+
+    let config = saved.0.with_settings(&ui_settings, &bindings);
+    if config != saved.0 {
+        if let Err(error) = config.save() {
+            println!("Failed to save settings: {}", error);
+        }
+        saved.0 = config;
+    }
+}
+
+fn session_path() -> io::Result<PathBuf> {
+    Ok(config_dir()?.join("session.tfer"))
+}
+
+/// The game in progress when the app was last closed, if it was saved
+pub fn load_session() -> io::Result<Option<SavedGame>> {
+    match SavedGame::load(&session_path()?) {
+        Ok(game) => Ok(Some(game)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn save_session(
+    mut exit: EventReader<AppExit>,
+    ui_settings: Res<UiSettings>,
+    board: Res<BoardResource>,
+    rng: Res<GameRng>,
+    metadata: Res<GameMetadata>,
+) {
+    if exit.iter().count() == 0 || !ui_settings.resume_last_game {
+        return;
+    }
+
+    let session = SavedGame::new(board.0.clone(), *rng, metadata.clone());
+    if let Err(error) = session_path().and_then(|path| session.save(&path)) {
+        println!("Failed to save game: {}", error);
+    }
+}
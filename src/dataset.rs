@@ -0,0 +1,219 @@
+//! Tools for turning recordings into training data.
+
+use clap::Args;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::{Board, Swipe, Symmetry},
+    record::{load_recording, serialize_recording, InoutPair},
+};
+
+#[derive(Clone, Default)]
+pub struct Dataset {
+    pub moves: Vec<InoutPair<Board, Swipe>>,
+}
+
+impl Dataset {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            moves: load_recording(&std::fs::read(path)?)?,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, serialize_recording(&self.moves))
+    }
+
+    pub fn merge(&mut self, other: Dataset) {
+        self.moves.extend(other.moves);
+    }
+
+    /// Removes repeated positions, keeping the first label seen for each.
+    /// Returns the number of removed samples.
+    pub fn dedupe(&mut self) -> usize {
+        let before = self.moves.len();
+        let mut seen = HashSet::new();
+        self.moves.retain(|pair| seen.insert(pair.input.clone()));
+        before - self.moves.len()
+    }
+
+    /// Expands every sample into its 8 symmetries, remapping the swipe to match
+    pub fn augment(&mut self) {
+        self.moves = self
+            .moves
+            .iter()
+            .flat_map(|pair| {
+                Symmetry::ALL.iter().map(move |symmetry| InoutPair {
+                    input: pair.input.transformed(*symmetry),
+                    output: pair.output.transformed(*symmetry),
+                })
+            })
+            .collect();
+    }
+
+    pub fn shuffle(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.moves.shuffle(&mut rng);
+    }
+
+    /// Splits into train, validation and test sets. The test set gets whatever
+    /// is left after the train and validation fractions.
+    pub fn split(&self, train: f32, validation: f32) -> (Dataset, Dataset, Dataset) {
+        let len = self.moves.len();
+        let train_end = ((len as f32 * train).round() as usize).min(len);
+        let validation_end =
+            ((len as f32 * (train + validation)).round() as usize).clamp(train_end, len);
+
+        let part = |range: std::ops::Range<usize>| Dataset {
+            moves: self.moves[range].to_vec(),
+        };
+        (
+            part(0..train_end),
+            part(train_end..validation_end),
+            part(validation_end..len),
+        )
+    }
+
+    pub fn stats(&self) -> DatasetStats {
+        let mut labels = [0; 4];
+        let mut max_tiles = [0; 32];
+        for pair in &self.moves {
+            labels[pair.output.index()] += 1;
+            let max_tile = pair.input.to_exponents().into_iter().max().unwrap();
+            max_tiles[(max_tile as usize).min(31)] += 1;
+        }
+
+        DatasetStats {
+            samples: self.moves.len(),
+            labels,
+            max_tiles,
+        }
+    }
+}
+
+pub struct DatasetStats {
+    pub samples: usize,
+    pub labels: [usize; 4],
+    pub max_tiles: [usize; 32],
+}
+
+impl std::fmt::Display for DatasetStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = |count: usize| 100.0 * count as f32 / self.samples.max(1) as f32;
+
+        writeln!(f, "{} samples", self.samples)?;
+        for swipe in Swipe::ALL {
+            let count = self.labels[swipe.index()];
+            writeln!(f, "  {:>5}: {:>7} ({:.1}%)", swipe, count, percent(count))?;
+        }
+        write!(f, "  max tile:")?;
+        for (exp, &count) in self.max_tiles.iter().enumerate() {
+            if count > 0 {
+                write!(f, " {}: {:.1}%", 1u32 << exp, percent(count))?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+/// Options for the `dataset` subcommand
+#[derive(Args)]
+pub struct DatasetArgs {
+    /// Recordings to merge
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Where to write the dataset. Splits add `_train`, `_validation` and `_test`.
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Remove repeated positions
+    #[arg(long)]
+    dedupe: bool,
+    /// Shuffle the positions with the given seed
+    #[arg(long, value_name = "SEED")]
+    shuffle: Option<u64>,
+    /// Split into train, validation and test sets by fraction
+    #[arg(long, value_name = "TRAIN,VALIDATION", value_parser = parse_split)]
+    split: Option<(f32, f32)>,
+    /// Add every rotation and reflection of each position
+    #[arg(long)]
+    augment: bool,
+}
+
+fn parse_split(fractions: &str) -> Result<(f32, f32), String> {
+    let fractions = fractions
+        .split(',')
+        .map(|fraction| fraction.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| error.to_string())?;
+    match fractions[..] {
+        [train, validation, ..] => Ok((train, validation)),
+        _ => Err("expected <train>,<validation>".to_string()),
+    }
+}
+
+/// Runs the dataset pipeline. Operations run in the order merge, dedupe,
+/// shuffle, split and augment, so that symmetric copies of a position never
+/// end up in different splits.
+pub fn run(args: DatasetArgs) -> io::Result<()> {
+    let DatasetArgs {
+        inputs,
+        output,
+        dedupe,
+        shuffle,
+        split,
+        augment,
+    } = args;
+
+    let mut dataset = Dataset::default();
+    for input in inputs {
+        dataset.merge(Dataset::load(&input)?);
+    }
+    print!("merge\n{}", dataset.stats());
+
+    if dedupe {
+        let removed = dataset.dedupe();
+        print!("dedupe (removed {})\n{}", removed, dataset.stats());
+    }
+
+    if let Some(seed) = shuffle {
+        dataset.shuffle(seed);
+        println!("shuffle (seed {})", seed);
+    }
+
+    let mut outputs = match split {
+        Some((train, validation)) => {
+            let (train_set, validation_set, test_set) = dataset.split(train, validation);
+            let stem = output.file_stem().unwrap().to_string_lossy();
+            vec![
+                (
+                    output.with_file_name(format!("{}_train.tfer", stem)),
+                    train_set,
+                ),
+                (
+                    output.with_file_name(format!("{}_validation.tfer", stem)),
+                    validation_set,
+                ),
+                (
+                    output.with_file_name(format!("{}_test.tfer", stem)),
+                    test_set,
+                ),
+            ]
+        }
+        None => vec![(output, dataset)],
+    };
+
+    for (path, dataset) in outputs.iter_mut() {
+        if augment {
+            dataset.augment();
+        }
+        dataset.save(path)?;
+        print!("{}\n{}", path.display(), dataset.stats());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+
+use crate::{
+    board::{Board, MAX_EXPONENT},
+    render::{Tile, UpdateBoardEvent},
+    BoardResource, GameMode,
+};
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorState>()
+            .add_systems(Update, (edit_tiles, editor_ui));
+    }
+}
+
+#[derive(Resource, Default)]
+struct EditorState {
+    message: Option<Result<(), String>>,
+}
+
+/// Left click raises a tile by one exponent, right click lowers it
+fn edit_tiles(
+    mode: Res<GameMode>,
+    mut board: ResMut<BoardResource>,
+    mut events: EventWriter<UpdateBoardEvent>,
+    mut contexts: EguiContexts,
+    mouse: Res<Input<MouseButton>>,
+    tiles: Query<(&Tile, &Interaction)>,
+) {
+    if *mode != GameMode::Edit || contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+
+    for (tile, interaction) in tiles.iter() {
+        if *interaction == Interaction::None {
+            continue;
+        }
+
+        let pos = tile.pos();
+        let exp = &mut board.data[pos.y][pos.x];
+        if mouse.just_pressed(MouseButton::Left) {
+            *exp = if *exp >= MAX_EXPONENT { 0 } else { *exp + 1 };
+            events.send(UpdateBoardEvent);
+        } else if mouse.just_pressed(MouseButton::Right) {
+            *exp = if *exp == 0 { MAX_EXPONENT } else { *exp - 1 };
+            events.send(UpdateBoardEvent);
+        }
+    }
+}
+
+fn editor_ui(
+    mut contexts: EguiContexts,
+    mut mode: ResMut<GameMode>,
+    mut board: ResMut<BoardResource>,
+    mut editor_state: ResMut<EditorState>,
+    mut events: EventWriter<UpdateBoardEvent>,
+) {
+    if *mode != GameMode::Edit {
+        return;
+    }
+
+    egui::Window::new("Editor").show(contexts.ctx_mut(), |ui| {
+        ui.label("Left click a tile to raise it, right click to lower it");
+
+        if ui.button("Clear board").clicked() {
+            let player_to_move = board.player_to_move;
+            board.0 = Board::new();
+            board.player_to_move = player_to_move;
+            events.send(UpdateBoardEvent);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Side to move");
+            ui.radio_value(&mut board.player_to_move, true, "Player");
+            ui.radio_value(&mut board.player_to_move, false, "Computer");
+        });
+
+        if ui.button("Validate").clicked() {
+            editor_state.message = Some(validate_position(&board));
+        }
+
+        match &editor_state.message {
+            Some(Ok(())) => {
+                ui.colored_label(egui::Color32::GREEN, "Position is valid");
+            }
+            Some(Err(error)) => {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            None => {}
+        }
+
+        if ui.button("Done").clicked() {
+            editor_state.message = validate_position(&board).err().map(Err);
+            if editor_state.message.is_none() {
+                *mode = GameMode::Play;
+            }
+        }
+    });
+}
+
+pub fn validate_position(board: &Board) -> Result<(), String> {
+    let exponents = board.to_exponents();
+    if exponents.iter().all(|&exp| exp == 0) {
+        return Err("The board is empty".to_string());
+    }
+    if exponents.iter().any(|&exp| exp > MAX_EXPONENT) {
+        return Err(format!("Tiles can be at most {}", 1u32 << MAX_EXPONENT));
+    }
+
+    match board.player_to_move {
+        true if board.get_moves().is_empty() => {
+            Err("The player has no moves, the game is already over".to_string())
+        }
+        false if !exponents.contains(&0) => {
+            Err("The computer has no empty tile to place on".to_string())
+        }
+        _ => Ok(()),
+    }
+}
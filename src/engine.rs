@@ -0,0 +1,352 @@
+//! The twenty forty-eight engine interface (tfei), a line based protocol
+//! modelled on UCI for playing with engines written in other languages.
+//!
+//! The app launches the engine and talks to it over stdin and stdout:
+//!
+//! ```text
+//! app:    tfei
+//! engine: id name Example
+//! engine: id author Someone
+//! engine: option name Depth type spin default 8 min 1 max 30
+//! engine: tfeiok
+//! app:    setoption name Depth value 6
+//! app:    isready
+//! engine: readyok
+//! app:    newgame
+//! app:    position 0000/0100/1230/567b p
+//! app:    go movetime 1000 depth 6
+//! engine: info depth 6
+//! engine: bestmove left
+//! app:    quit
+//! ```
+//!
+//! Positions are written in board notation (see [`crate::notation`]), and
+//! `go` can limit the search by time in milliseconds, depth in plies, or both.
+//! The engine answers with `bestmove up|down|left|right`, or `bestmove none`
+//! if it has no move. An engine that runs out of time is sent `stop`, which
+//! should make it answer at once. Lines either side doesn't understand are
+//! ignored.
+//!
+//! The reference engine is the `tfei-engine` binary, or the `engine`
+//! subcommand of the app.
+
+use std::{
+    ffi::OsStr,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::{Board, Swipe},
+    notation::Notation,
+    strategy::{Search, Strategy},
+};
+
+/// How long an engine gets to answer anything that isn't a search
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long past its time limit an engine gets to answer `go`
+const MOVE_GRACE: Duration = Duration::from_secs(5);
+/// How long an engine gets to answer `go` without a time limit
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A running engine process
+pub struct Engine {
+    pub name: String,
+    pub options: Vec<String>,
+    child: Child,
+    stdin: ChildStdin,
+    // stdout is read on its own thread so reads can time out
+    lines: Receiver<String>,
+    /// Set when the engine stopped answering, so it isn't asked again
+    dead: bool,
+}
+
+impl Engine {
+    /// Launches the engine and waits for it to finish the handshake
+    pub fn spawn(program: impl AsRef<OsStr>, args: &[&str]) -> io::Result<Self> {
+        let program = program.as_ref();
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            name: program.to_string_lossy().into_owned(),
+            options: Vec::new(),
+            child,
+            stdin,
+            lines,
+            dead: false,
+        };
+
+        engine.send("tfei")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.read_line(deadline)?;
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("id"), Some("name")) => engine.name = words.collect::<Vec<_>>().join(" "),
+                (Some("option"), Some("name")) => {
+                    let name = words.take_while(|word| *word != "type");
+                    engine.options.push(name.collect::<Vec<_>>().join(" "));
+                }
+                (Some("tfeiok"), _) => break,
+                _ => {}
+            }
+        }
+
+        Ok(engine)
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()
+    }
+
+    fn read_line(&mut self, deadline: Instant) -> io::Result<String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|error| {
+            let kind = match error {
+                RecvTimeoutError::Timeout => io::ErrorKind::TimedOut,
+                RecvTimeoutError::Disconnected => io::ErrorKind::UnexpectedEof,
+            };
+            io::Error::new(kind, format!("{} stopped responding", self.name))
+        })
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        if !self
+            .options
+            .iter()
+            .any(|option| option.eq_ignore_ascii_case(name))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} has no option {}", self.name, name),
+            ));
+        }
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    /// Waits until the engine has finished processing everything sent so far
+    pub fn is_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.read_line(deadline)? != "readyok" {}
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("newgame")
+    }
+
+    /// Asks for a swipe, with at least one of the time or depth limits set.
+    /// An engine that doesn't answer in time, even after `stop`, is never
+    /// asked again.
+    pub fn best_move(
+        &mut self,
+        board: &Board,
+        movetime: Option<Duration>,
+        depth: Option<u8>,
+    ) -> io::Result<Option<Swipe>> {
+        if self.dead {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                format!("{} stopped responding", self.name),
+            ));
+        }
+
+        let result = self.search(board, movetime, depth);
+        if let Err(error) = &result {
+            if error.kind() == io::ErrorKind::TimedOut {
+                // the late answer would otherwise be read as the next move
+                self.dead = self.stop().is_err();
+            } else if error.kind() != io::ErrorKind::InvalidData {
+                self.dead = true;
+            }
+        }
+        result
+    }
+
+    fn search(
+        &mut self,
+        board: &Board,
+        movetime: Option<Duration>,
+        depth: Option<u8>,
+    ) -> io::Result<Option<Swipe>> {
+        self.send(&format!("position {}", Notation(board.clone())))?;
+
+        let mut go = "go".to_string();
+        if let Some(movetime) = movetime {
+            go += &format!(" movetime {}", movetime.as_millis());
+        }
+        if let Some(depth) = depth {
+            go += &format!(" depth {}", depth);
+        }
+        self.send(&go)?;
+
+        let timeout = movetime.map_or(SEARCH_TIMEOUT, |movetime| movetime + MOVE_GRACE);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let line = self.read_line(deadline)?;
+            if let Some(swipe) = line.strip_prefix("bestmove") {
+                return match swipe.trim() {
+                    "none" => Ok(None),
+                    swipe => swipe
+                        .parse()
+                        .map(Some)
+                        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+                };
+            }
+        }
+    }
+
+    /// Ends a search that ran out of time, reading its answer so it isn't
+    /// taken for the next one
+    fn stop(&mut self) -> io::Result<()> {
+        self.send("stop")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while !self.read_line(deadline)?.starts_with("bestmove") {}
+        Ok(())
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+const STRATEGIES: [&str; 5] = ["negamax", "parallel", "mcts", "expectimax", "random"];
+
+/// The reference engine, which plays with the built in search strategies.
+/// The given strategy and depth are used until the app sets other options.
+pub fn serve(
+    mut strategy: Strategy,
+    mut depth: u8,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut search = Search::new(strategy.clone());
+    let mut board = None;
+
+    for line in input.lines() {
+        let line = line?;
+        let (command, arguments) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match command {
+            "tfei" => {
+                writeln!(output, "id name twenty-forty-eight")?;
+                writeln!(output, "id author twenty-forty-eight")?;
+                let vars: Vec<String> = STRATEGIES
+                    .iter()
+                    .map(|name| format!("var {}", name))
+                    .collect();
+                writeln!(
+                    output,
+                    "option name Strategy type combo default {} {}",
+                    strategy.name(),
+                    vars.join(" ")
+                )?;
+                writeln!(
+                    output,
+                    "option name Depth type spin default {} min 1 max 30",
+                    depth
+                )?;
+                writeln!(output, "tfeiok")?;
+            }
+            "setoption" => {
+                let Some((name, value)) = parse_option(arguments) else {
+                    continue;
+                };
+                match name.to_lowercase().as_str() {
+                    "strategy" => match value.parse::<Strategy>() {
+                        Ok(new_strategy) if new_strategy != strategy => {
+                            strategy = new_strategy;
+                            search = Search::new(strategy.clone());
+                        }
+                        Ok(_) => {}
+                        Err(error) => writeln!(output, "info string {}", error)?,
+                    },
+                    "depth" => match value.parse() {
+                        Ok(new_depth) => depth = new_depth,
+                        Err(_) => writeln!(output, "info string Invalid depth: {}", value)?,
+                    },
+                    _ => writeln!(output, "info string Unknown option: {}", name)?,
+                }
+            }
+            "isready" => writeln!(output, "readyok")?,
+            "newgame" => board = None,
+            "position" => match arguments.parse::<Notation>() {
+                Ok(Notation(new_board)) => board = Some(new_board),
+                Err(error) => writeln!(output, "info string {}", error)?,
+            },
+            "go" => {
+                let (movetime, go_depth) = parse_go(arguments);
+                search.set_timeout(movetime);
+                let go_depth = go_depth.unwrap_or(depth);
+
+                let swipe = board
+                    .as_ref()
+                    .filter(|board| board.player_to_move)
+                    .and_then(|board| search.choose_move(board, go_depth));
+                writeln!(output, "info depth {}", go_depth)?;
+                match swipe {
+                    Some(swipe) => writeln!(output, "bestmove {}", swipe)?,
+                    None => writeln!(output, "bestmove none")?,
+                }
+            }
+            "quit" => break,
+            _ => {}
+        }
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Splits `name <name> value <value>`, where the name can contain spaces
+fn parse_option(arguments: &str) -> Option<(&str, &str)> {
+    let arguments = arguments.strip_prefix("name ")?;
+    match arguments.split_once(" value ") {
+        Some((name, value)) => Some((name.trim(), value.trim())),
+        None => Some((arguments.trim(), "")),
+    }
+}
+
+fn parse_go(arguments: &str) -> (Option<Duration>, Option<u8>) {
+    let mut movetime = None;
+    let mut depth = None;
+
+    let mut words = arguments.split_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            "movetime" => {
+                movetime = words
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .map(Duration::from_millis)
+            }
+            "depth" => depth = words.next().and_then(|value| value.parse().ok()),
+            _ => {}
+        }
+    }
+
+    (movetime, depth)
+}
@@ -0,0 +1,235 @@
+//! A reinforcement learning environment in the style of Gym, built on
+//! [`Game`] so it can be used without Bevy.
+//!
+//! Observations are the 16 tile exponents in row-major order, and actions are
+//! swipe indices (see [`Swipe::from_index`]).
+
+use std::thread;
+
+use crate::{
+    board::{Moves, Swipe},
+    game::Game,
+};
+
+pub type Observation = [u8; 16];
+
+/// How rewards are made up. Every part is added together each step.
+#[derive(Debug, Clone)]
+pub struct RewardConfig {
+    /// Multiplies the score gained from merging tiles
+    pub merge: f32,
+    /// Given for every swipe that changes the board
+    pub survival: f32,
+    /// Given each time the largest tile reaches a new power of two, starting
+    /// from `milestone_exponent`
+    pub milestone: f32,
+    pub milestone_exponent: u8,
+    /// Given for swipes that don't change the board, which leave the game as it was
+    pub invalid: f32,
+    /// Given on the step that ends the game
+    pub game_over: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            merge: 1.0,
+            survival: 0.0,
+            milestone: 0.0,
+            milestone_exponent: 9,
+            invalid: -1.0,
+            game_over: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StepInfo {
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+    /// False if the action didn't change the board
+    pub valid: bool,
+    /// The legal actions in the new position
+    pub action_mask: [bool; 4],
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f32,
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+#[derive(Debug, Clone)]
+pub struct Env {
+    game: Game,
+    rewards: RewardConfig,
+}
+
+impl Env {
+    pub fn new(rewards: RewardConfig) -> Self {
+        Self {
+            game: Game::new(0),
+            rewards,
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::new(seed);
+        self.observation()
+    }
+
+    pub fn step(&mut self, action: usize) -> Step {
+        let rewards = &self.rewards;
+        let before = self.game.score();
+        let max_before = self.max_exponent();
+
+        let mut reward = 0.0;
+        let valid = match Swipe::from_index(action) {
+            Some(swipe) => {
+                // the merge score is measured before the computer places its tile
+                let mut swiped = self.game.board.clone();
+                let valid = swiped.swipe(swipe);
+                if valid {
+                    reward += rewards.merge * (swiped.score() - before) as f32;
+                    self.game.play(swipe);
+                }
+                valid
+            }
+            None => false,
+        };
+
+        if valid {
+            reward += rewards.survival;
+            for exponent in (max_before + 1)..=self.max_exponent() {
+                if exponent >= rewards.milestone_exponent {
+                    reward += rewards.milestone;
+                }
+            }
+        } else {
+            reward += rewards.invalid;
+        }
+
+        let done = self.game.is_over();
+        if done {
+            reward += rewards.game_over;
+        }
+
+        Step {
+            observation: self.observation(),
+            reward,
+            done,
+            info: StepInfo {
+                score: self.game.score(),
+                max_tile: self.game.max_tile(),
+                moves: self.game.moves,
+                valid,
+                action_mask: self.action_mask(),
+            },
+        }
+    }
+
+    pub fn observation(&self) -> Observation {
+        self.game.to_exponents()
+    }
+
+    /// Which swipes would change the board, indexed like the actions
+    pub fn action_mask(&self) -> [bool; 4] {
+        let mut mask = [false; 4];
+        for moves in self.game.get_moves() {
+            if let Moves::Player(swipe) = moves {
+                mask[swipe.index()] = true;
+            }
+        }
+        mask
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    fn max_exponent(&self) -> u8 {
+        self.observation().into_iter().max().unwrap_or(0)
+    }
+}
+
+/// Many environments stepped together across threads. Finished games are
+/// reset straight away, so the observation returned with `done` is the start
+/// of the next game while the info still describes the one that ended.
+pub struct VecEnv {
+    envs: Vec<Env>,
+    next_seed: u64,
+}
+
+impl VecEnv {
+    pub fn new(count: usize, rewards: RewardConfig) -> Self {
+        Self {
+            envs: vec![Env::new(rewards); count],
+            next_seed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    /// Resets every environment, seeding them `seed`, `seed + 1`, ...
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.next_seed = seed.wrapping_add(self.envs.len() as u64);
+        self.envs
+            .iter_mut()
+            .enumerate()
+            .map(|(i, env)| env.reset(seed.wrapping_add(i as u64)))
+            .collect()
+    }
+
+    /// Takes one action per environment
+    pub fn step(&mut self, actions: &[usize]) -> Vec<Step> {
+        assert_eq!(
+            actions.len(),
+            self.envs.len(),
+            "Expected one action per environment"
+        );
+
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let chunk_size = self.envs.len().div_ceil(threads).max(1);
+
+        let mut steps: Vec<Step> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .envs
+                .chunks_mut(chunk_size)
+                .zip(actions.chunks(chunk_size))
+                .map(|(envs, actions)| {
+                    scope.spawn(move || {
+                        envs.iter_mut()
+                            .zip(actions)
+                            .map(|(env, &action)| env.step(action))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        for (env, step) in self.envs.iter_mut().zip(steps.iter_mut()) {
+            if step.done {
+                step.observation = env.reset(self.next_seed);
+                self.next_seed = self.next_seed.wrapping_add(1);
+            }
+        }
+        steps
+    }
+
+    pub fn action_masks(&self) -> Vec<[bool; 4]> {
+        self.envs.iter().map(Env::action_mask).collect()
+    }
+}
@@ -20,8 +20,13 @@ impl minimax::Game for TwentyFortyEight {
         Some(board)
     }
 
-    fn get_winner(_: &Board) -> Option<Winner> {
-        None
+    fn get_winner(board: &Board) -> Option<Winner> {
+        // the player loses once they can't swipe, which rollouts need to know
+        if board.player_to_move && !board.can_swipe() {
+            Some(Winner::PlayerJustMoved)
+        } else {
+            None
+        }
     }
 
     fn zobrist_hash(board: &Board) -> u64 {
@@ -0,0 +1,208 @@
+//! Conversion of `.tfer` recordings to formats that other tools can read.
+//!
+//! Boards are always written as 16 tile exponents in row-major order (0 is an
+//! empty tile, 1 is a 2, 2 is a 4, ...). Actions are written by name in JSON
+//! Lines and CSV, and as the recording index (0 up, 1 down, 2 left, 3 right)
+//! in `.npy` files.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::{Board, Swipe},
+    record::{load_recording, serialize_recording, InoutPair},
+};
+
+#[derive(Serialize, Deserialize)]
+struct MoveLine {
+    board: [u8; 16],
+    action: String,
+}
+
+pub fn export_jsonl(moves: &[InoutPair<Board, Swipe>], output: &mut impl Write) -> io::Result<()> {
+    for pair in moves {
+        let line = MoveLine {
+            board: pair.input.to_exponents(),
+            action: pair.output.to_string(),
+        };
+        serde_json::to_writer(&mut *output, &line)?;
+        output.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+pub fn import_jsonl(input: impl BufRead) -> io::Result<Vec<InoutPair<Board, Swipe>>> {
+    let mut moves = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let invalid = |error: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", i + 1, error),
+            )
+        };
+        let line: MoveLine = serde_json::from_str(&line).map_err(|e| invalid(e.to_string()))?;
+        let swipe = line.action.parse().map_err(invalid)?;
+
+        moves.push(InoutPair {
+            input: Board::from_exponents(line.board),
+            output: swipe,
+        });
+    }
+    Ok(moves)
+}
+
+pub fn export_csv(moves: &[InoutPair<Board, Swipe>], output: &mut impl Write) -> io::Result<()> {
+    for i in 0..16 {
+        write!(output, "t{},", i)?;
+    }
+    writeln!(output, "action")?;
+
+    for pair in moves {
+        for exp in pair.input.to_exponents() {
+            write!(output, "{},", exp)?;
+        }
+        writeln!(output, "{}", pair.output)?;
+    }
+    Ok(())
+}
+
+/// Writes the boards as a (N, 16) array and the actions as a (N,) array, both `uint8`
+pub fn export_npy(
+    moves: &[InoutPair<Board, Swipe>],
+    boards: &mut impl Write,
+    actions: &mut impl Write,
+) -> io::Result<()> {
+    write_npy_header(boards, &format!("({}, 16)", moves.len()))?;
+    for InoutPair { input, .. } in moves {
+        boards.write_all(&input.to_exponents())?;
+    }
+
+    write_npy_header(actions, &format!("({},)", moves.len()))?;
+    for InoutPair { output, .. } in moves {
+        actions.write_all(&[output.index() as u8])?;
+    }
+    Ok(())
+}
+
+fn write_npy_header(output: &mut impl Write, shape: &str) -> io::Result<()> {
+    let mut header = format!(
+        "{{'descr': '|u1', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+
+    // magic, version and header length take 10 bytes, and the whole header
+    // has to be padded to a multiple of 64 ending in a newline
+    let padding = 64 - (10 + header.len() + 1) % 64;
+    header.push_str(&" ".repeat(padding % 64));
+    header.push('\n');
+
+    output.write_all(b"\x93NUMPY\x01\x00")?;
+    output.write_all(&(header.len() as u16).to_le_bytes())?;
+    output.write_all(header.as_bytes())
+}
+
+/// Converts between recording formats based on the file extensions.
+///
+/// `.tfer` files can be exported to `.jsonl`, `.csv` or `.npy`, and `.jsonl`
+/// files can be imported back to `.tfer`. For `.npy` the output path is used
+/// as a prefix for `<name>_boards.npy` and `<name>_actions.npy`.
+pub fn convert(input: &Path, output: &Path) -> io::Result<()> {
+    let extension = |path: &Path| {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("")
+            .to_lowercase()
+    };
+
+    let moves = match extension(input).as_str() {
+        "tfer" => load_recording(&std::fs::read(input)?)?,
+        "jsonl" => import_jsonl(io::BufReader::new(std::fs::File::open(input)?))?,
+        other => return Err(unsupported(other)),
+    };
+
+    let create = |path: &Path| std::fs::File::create(path).map(io::BufWriter::new);
+    match extension(output).as_str() {
+        "tfer" => std::fs::write(output, serialize_recording(&moves))?,
+        "jsonl" => export_jsonl(&moves, &mut create(output)?)?,
+        "csv" => export_csv(&moves, &mut create(output)?)?,
+        "npy" => {
+            let (boards, actions) = npy_paths(output);
+            export_npy(&moves, &mut create(&boards)?, &mut create(&actions)?)?;
+        }
+        other => return Err(unsupported(other)),
+    }
+
+    println!("Converted {} moves", moves.len());
+    Ok(())
+}
+
+fn npy_paths(output: &Path) -> (PathBuf, PathBuf) {
+    let stem = output.file_stem().unwrap().to_string_lossy();
+    (
+        output.with_file_name(format!("{}_boards.npy", stem)),
+        output.with_file_name(format!("{}_actions.npy", stem)),
+    )
+}
+
+fn unsupported(extension: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Unsupported file type: .{}", extension),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves() -> Vec<InoutPair<Board, Swipe>> {
+        let mut exponents = [0; 16];
+        exponents[1] = 1;
+        exponents[6] = 11;
+        vec![
+            InoutPair {
+                input: Board::from_exponents(exponents),
+                output: Swipe::Left,
+            },
+            InoutPair {
+                input: Board::from_exponents([2; 16]),
+                output: Swipe::Down,
+            },
+        ]
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        let mut output = Vec::new();
+        export_jsonl(&moves(), &mut output).unwrap();
+        let imported = import_jsonl(output.as_slice()).unwrap();
+
+        assert_eq!(imported.len(), 2);
+        for (imported, original) in imported.iter().zip(moves()) {
+            assert_eq!(imported.input, original.input);
+            assert_eq!(imported.output, original.output);
+        }
+    }
+
+    #[test]
+    fn npy_header_is_padded() {
+        for shape in ["(0,)", "(2, 16)", "(123456789, 16)"] {
+            let mut output = Vec::new();
+            write_npy_header(&mut output, shape).unwrap();
+
+            assert_eq!(output.len() % 64, 0);
+            assert_eq!(&output[..8], b"\x93NUMPY\x01\x00");
+            let header_len = u16::from_le_bytes([output[8], output[9]]) as usize;
+            assert_eq!(header_len + 10, output.len());
+            assert_eq!(output.last(), Some(&b'\n'));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use rand::{rngs::StdRng, SeedableRng};
+use std::ops::{Deref, DerefMut};
+
+use crate::board::{Board, Swipe};
+
+/// A board played with seeded tile placements, so the same seed and swipes
+/// always give the same game
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub board: Board,
+    pub seed: u64,
+    /// How many times tiles were placed, the starting tiles counting as one
+    pub placed: u32,
+    pub moves: u32,
+}
+
+impl Game {
+    pub fn new(seed: u64) -> Self {
+        let mut game = Self::from_board(Board::new(), seed);
+        let mut rng = game.next_rng();
+        game.board.add_random_with(&mut rng);
+        game.board.add_random_with(&mut rng);
+        game
+    }
+
+    pub fn from_board(board: Board, seed: u64) -> Self {
+        Self::resume(board, seed, 0)
+    }
+
+    /// Continues a game after tiles were placed `placed` times
+    pub fn resume(board: Board, seed: u64, placed: u32) -> Self {
+        Self {
+            board,
+            seed,
+            placed,
+            moves: 0,
+        }
+    }
+
+    /// The generator for the next tile placement
+    fn next_rng(&mut self) -> StdRng {
+        let rng = tile_rng(self.seed, self.placed);
+        self.placed += 1;
+        rng
+    }
+
+    /// Swipes and places the computers tile. Returns true if the board was changed.
+    pub fn play(&mut self, swipe: Swipe) -> bool {
+        let mut board = self.board.clone();
+        if !board.swipe(swipe) {
+            return false;
+        }
+
+        board.computer_move_with(&mut self.next_rng());
+        self.board = board;
+        self.moves += 1;
+        true
+    }
+
+    /// Places the computers tile if it is their turn
+    pub fn computer_move(&mut self) {
+        if !self.board.player_to_move {
+            let mut rng = self.next_rng();
+            self.board.computer_move_with(&mut rng);
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.player_to_move && !self.board.can_swipe()
+    }
+}
+
+impl Deref for Game {
+    type Target = Board;
+
+    fn deref(&self) -> &Board {
+        &self.board
+    }
+}
+
+impl DerefMut for Game {
+    fn deref_mut(&mut self) -> &mut Board {
+        &mut self.board
+    }
+}
+
+/// The generator for the tiles placed after tiles were already placed `placed`
+/// times. Each placement gets its own generator so a game can be resumed from
+/// just the seed and placement count.
+pub fn tile_rng(seed: u64, placed: u32) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (placed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
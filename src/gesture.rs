@@ -0,0 +1,85 @@
+//! Swipes made by dragging the mouse or a finger across the board. They are
+//! sent as the same [`GameCommand`]s as the arrow keys.
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{board::Swipe, render::BoardBackground, ui::UiSettings, GameCommand};
+
+pub struct GesturePlugin;
+
+impl Plugin for GesturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, detect_swipes);
+    }
+}
+
+/// The swipe made by dragging `delta` logical pixels, if the drag is at least
+/// `min_distance` long and its longer axis is at least `direction_ratio` times
+/// the shorter one
+fn swipe_from_drag(delta: Vec2, min_distance: f32, direction_ratio: f32) -> Option<Swipe> {
+    let (x, y) = (delta.x.abs(), delta.y.abs());
+    if x.max(y) < min_distance {
+        None
+    } else if x >= y * direction_ratio {
+        Some(if delta.x > 0.0 {
+            Swipe::Right
+        } else {
+            Swipe::Left
+        })
+    } else if y >= x * direction_ratio {
+        // window coordinates grow downwards
+        Some(if delta.y > 0.0 {
+            Swipe::Down
+        } else {
+            Swipe::Up
+        })
+    } else {
+        None
+    }
+}
+
+fn detect_swipes(
+    board: Query<&Interaction, With<BoardBackground>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mouse: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    ui_settings: Res<UiSettings>,
+    mut start: Local<Option<Vec2>>,
+    mut game_commands: EventWriter<GameCommand>,
+) {
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+
+    // drags have to start on the board, but can end anywhere
+    if board
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        if mouse.just_pressed(MouseButton::Left) {
+            *start = cursor;
+        } else if let Some(touch) = touches.iter_just_pressed().next() {
+            *start = Some(touch.position());
+        }
+    }
+
+    let end = if mouse.just_released(MouseButton::Left) {
+        cursor
+    } else if let Some(touch) = touches.iter_just_released().next() {
+        Some(touch.position())
+    } else {
+        return;
+    };
+
+    if let (Some(start), Some(end)) = (start.take(), end) {
+        let swipe = swipe_from_drag(
+            end - start,
+            ui_settings.swipe_distance,
+            ui_settings.swipe_ratio,
+        );
+        if let Some(swipe) = swipe {
+            game_commands.send(GameCommand::Swipe(swipe));
+        }
+    }
+}
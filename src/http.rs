@@ -0,0 +1,206 @@
+//! A local HTTP API for driving and watching the game from scripts. It is
+//! off unless the app is started with `gui --http [port]`, and only listens
+//! on 127.0.0.1.
+//!
+//! - `GET /state` returns the board, score and settings
+//! - `POST /swipe` with `{"swipe": "left"}`
+//! - `POST /reset` with `{"seed": 42}`, where the seed is optional
+//! - `POST /autoplay` with `{"enabled": true}`, or no body to toggle it
+//! - `POST /depth` with `{"depth": 6}`
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    board::{Moves, Swipe},
+    notation::Notation,
+    ui::UiSettings,
+    BoardResource, GameCommand, GameMode,
+};
+
+pub struct HttpPlugin {
+    pub port: u16,
+}
+
+impl Plugin for HttpPlugin {
+    fn build(&self, app: &mut App) {
+        let server = match Server::http(("127.0.0.1", self.port)) {
+            Ok(server) => server,
+            Err(error) => {
+                println!("Failed to start the HTTP server: {}", error);
+                return;
+            }
+        };
+        println!("Listening on http://127.0.0.1:{}", self.port);
+
+        // requests are answered from a system so they see the same state as the window
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                if sender.send(request).is_err() {
+                    break;
+                }
+            }
+        });
+
+        app.insert_resource(HttpRequests(Mutex::new(receiver)))
+            .add_systems(Update, handle_requests);
+    }
+}
+
+#[derive(Resource)]
+struct HttpRequests(Mutex<Receiver<Request>>);
+
+#[derive(Deserialize)]
+struct SwipeBody {
+    swipe: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ResetBody {
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct AutoplayBody {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct DepthBody {
+    depth: u8,
+}
+
+fn handle_requests(
+    requests: Res<HttpRequests>,
+    board: Res<BoardResource>,
+    ui_settings: Res<UiSettings>,
+    mode: Res<GameMode>,
+    mut game_commands: EventWriter<GameCommand>,
+) {
+    let requests = requests.0.lock().unwrap();
+    for mut request in requests.try_iter() {
+        let mut body = String::new();
+        let read = request.as_reader().read_to_string(&mut body);
+        let result = match read {
+            Ok(_) => route(
+                request.method(),
+                request.url(),
+                &body,
+                &board,
+                &ui_settings,
+                *mode,
+            ),
+            Err(error) => Err((400, error.to_string())),
+        };
+
+        let (status, value) = match result {
+            Ok((command, value)) => {
+                if let Some(command) = command {
+                    game_commands.send(command);
+                }
+                (200, value)
+            }
+            Err((status, error)) => (status, json!({ "error": error })),
+        };
+
+        let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(error) = request.respond(response) {
+            println!("Failed to answer HTTP request: {}", error);
+        }
+    }
+}
+
+/// Returns the command to run along with the response, or a status and error
+fn route(
+    method: &Method,
+    url: &str,
+    body: &str,
+    board: &BoardResource,
+    ui_settings: &UiSettings,
+    mode: GameMode,
+) -> Result<(Option<GameCommand>, Value), (u16, String)> {
+    match (method, url) {
+        (Method::Get, "/state") => Ok((None, state(board, ui_settings, mode))),
+        (Method::Post, "/swipe") => {
+            let body: SwipeBody = parse_body(body)?;
+            let swipe: Swipe = body.swipe.parse().map_err(|error| (400, error))?;
+            if mode != GameMode::Play {
+                return Err((409, "The game is not being played".to_string()));
+            }
+            if !board.get_moves().contains(&Moves::Player(swipe)) {
+                return Err((409, format!("Can't swipe {} here", swipe)));
+            }
+            Ok((Some(GameCommand::Swipe(swipe)), json!({ "ok": true })))
+        }
+        (Method::Post, "/reset") => {
+            let body: ResetBody = match body.trim().is_empty() {
+                true => ResetBody::default(),
+                false => parse_body(body)?,
+            };
+            let command = GameCommand::Reset { seed: body.seed };
+            Ok((Some(command), json!({ "ok": true })))
+        }
+        (Method::Post, "/autoplay") => {
+            let enabled = match body.trim().is_empty() {
+                true => !ui_settings.automatic,
+                false => parse_body::<AutoplayBody>(body)?.enabled,
+            };
+            let command = GameCommand::SetAutomatic(enabled);
+            Ok((Some(command), json!({ "automatic": enabled })))
+        }
+        (Method::Post, "/depth") => {
+            let body: DepthBody = parse_body(body)?;
+            if body.depth == 0 {
+                return Err((400, "Depth must be at least 1".to_string()));
+            }
+            let command = GameCommand::SetDepth(body.depth);
+            Ok((Some(command), json!({ "depth": body.depth })))
+        }
+        _ => Err((404, format!("No endpoint {} {}", method, url))),
+    }
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, (u16, String)> {
+    serde_json::from_str(body).map_err(|error| (400, error.to_string()))
+}
+
+fn state(board: &BoardResource, ui_settings: &UiSettings, mode: GameMode) -> Value {
+    let tiles: Vec<Vec<u32>> = board
+        .data
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&exp| if exp == 0 { 0 } else { 1 << exp })
+                .collect()
+        })
+        .collect();
+    let mode = match mode {
+        GameMode::Play => "play",
+        GameMode::Replay => "replay",
+        GameMode::Edit => "edit",
+    };
+
+    json!({
+        "board": tiles,
+        "notation": Notation(board.0.clone()).to_string(),
+        "score": board.score(),
+        "player_to_move": board.player_to_move,
+        "game_over": board.player_to_move && board.get_moves().is_empty(),
+        "mode": mode,
+        "automatic": ui_settings.automatic,
+        "depth": ui_settings.depth,
+    })
+}
@@ -0,0 +1,463 @@
+use bevy::{prelude::*, tasks::Task};
+use bindings::BindingsPlugin;
+use board::{Board, Swipe};
+use clap::Parser;
+use cli::{Cli, Command, SearchArgs};
+use config::{Config, ConfigPlugin};
+use editor::EditorPlugin;
+use futures_lite::future;
+use game::Game;
+use gesture::GesturePlugin;
+use http::HttpPlugin;
+use player::{
+    Decision, EngineResource, ExternalPlayer, KeyboardPlayer, Player, PolicyPlayer, SearchPlayer,
+};
+use policy::PolicyResource;
+use record::{GameMetadata, InoutPair, RecordEvent, RecordPlugin};
+use render::{BoardPlugin, UpdateBoardEvent};
+use stats::{GameOver, StatsPlugin};
+use std::time::Duration;
+use theme::Themes;
+use ui::{AutoPlayer, UIPlugin, UiSettings};
+
+pub mod analysis;
+pub mod bindings;
+pub mod board;
+pub mod cli;
+pub mod config;
+pub mod dataset;
+pub mod editor;
+pub mod engine;
+pub mod env;
+pub mod evaluators;
+pub mod export;
+pub mod game;
+pub mod gesture;
+pub mod http;
+pub mod notation;
+pub mod player;
+pub mod policy;
+#[cfg(feature = "python")]
+mod python;
+pub mod record;
+pub mod render;
+pub mod slots;
+pub mod stats;
+pub mod strategy;
+pub mod theme;
+pub mod tui;
+pub mod ui;
+
+/// Runs a subcommand if one is given, and the game window otherwise
+pub fn run() {
+    let Cli { search, command } = Cli::parse();
+    let command = command.unwrap_or(Command::Gui { http: None });
+    let seed = search.seed.unwrap_or_else(rand::random);
+
+    let (name, result) = match command {
+        Command::Gui { http } => {
+            run_gui(&search, http);
+            return;
+        }
+        Command::Simulate(args) => (
+            "Simulation",
+            player::simulate(args, search.strategy(), search.depth.unwrap_or(6), seed),
+        ),
+        Command::Replay { file, delay } => (
+            "Replay",
+            record::replay(&file, Duration::from_millis(delay)),
+        ),
+        Command::Convert { input, output } => ("Conversion", export::convert(&input, &output)),
+        Command::Analyze(args) => (
+            "Analysis",
+            analysis::run(
+                args,
+                search.depth.unwrap_or(8),
+                search.threads(),
+                search.table_size(),
+            ),
+        ),
+        Command::Tui => {
+            let theme = Config::load().unwrap_or_default().theme;
+            (
+                "Terminal frontend",
+                tui::run(
+                    search.strategy(),
+                    search.depth.unwrap_or(6),
+                    seed,
+                    Themes::load().get(&theme).clone(),
+                ),
+            )
+        }
+        Command::Dataset(args) => ("Dataset", dataset::run(args)),
+        Command::Train(args) => ("Training", policy::run(args, search.seed.unwrap_or(0))),
+        Command::Tournament(args) => (
+            "Tournament",
+            player::run_tournament(args, search.depth.unwrap_or(6), search.seed.unwrap_or(0)),
+        ),
+        Command::Engine => (
+            "Engine",
+            engine::serve(
+                search.strategy(),
+                search.depth.unwrap_or(8),
+                std::io::stdin().lock(),
+                std::io::stdout().lock(),
+            ),
+        ),
+    };
+
+    if let Err(error) = result {
+        eprintln!("{} failed: {}", name, error);
+        std::process::exit(1);
+    }
+}
+
+fn run_gui(search: &SearchArgs, http: Option<u16>) {
+    let config = Config::load().unwrap_or_else(|error| {
+        println!("Failed to load config: {}", error);
+        Config::default()
+    });
+    let ui_settings = config.settings(search);
+    let launch_config = config.with_settings(&ui_settings, &config.bindings);
+
+    // a seed on the command line asks for a new game
+    let session = match search.seed {
+        None if ui_settings.resume_last_game => config::load_session().unwrap_or_else(|error| {
+            println!("Failed to resume last game: {}", error);
+            None
+        }),
+        _ => None,
+    };
+
+    let mut app = App::new();
+    // inserted before the plugins so they aren't replaced with defaults
+    app.insert_resource(ui_settings)
+        .insert_resource(config.bindings.clone());
+    match session {
+        Some(session) => {
+            app.insert_resource(BoardResource(session.board))
+                .insert_resource(session.rng)
+                .insert_resource(session.metadata);
+        }
+        None => {
+            app.insert_resource(search.seed.map(GameRng::new).unwrap_or_default());
+        }
+    }
+    app.add_plugins((
+        DefaultPlugins,
+        BoardPlugin,
+        UIPlugin,
+        RecordPlugin,
+        EditorPlugin,
+        StatsPlugin,
+        GesturePlugin,
+        BindingsPlugin,
+        ConfigPlugin {
+            config: launch_config,
+        },
+    ))
+    .add_event::<GameCommand>()
+    .init_resource::<MoveTimer>()
+    .init_resource::<GameMode>()
+    .init_resource::<GameRng>()
+    .init_resource::<PolicyResource>()
+    .init_resource::<EngineResource>()
+    .init_resource::<Players>()
+    .add_systems(Startup, setup)
+    .add_systems(
+        Update,
+        (apply_commands, update_players, update, tick_clock).chain(),
+    );
+
+    if let Some(port) = http {
+        app.add_plugins(HttpPlugin { port });
+    }
+
+    app.run();
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct BoardResource(Board);
+
+/// What the board is currently being used for. Moves are only made while playing.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Play,
+    Replay,
+    Edit,
+}
+
+/// Places the computers tiles, so a game can be replayed or resumed from
+/// its seed and the number of times tiles were placed
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GameRng {
+    pub seed: u64,
+    pub placed: u32,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, placed: 0 }
+    }
+
+    /// The game on `board` placing its tiles from this generator. Copy its
+    /// `placed` count back after playing it.
+    fn game(&self, board: Board) -> Game {
+        Game::resume(board, self.seed, self.placed)
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+/// Everything that can change the live game, whether it comes from the
+/// keyboard, the settings window or the HTTP API
+#[derive(Event, Clone, Copy, Debug)]
+pub enum GameCommand {
+    Swipe(Swipe),
+    /// Starts a new game, seeded randomly if no seed is given
+    Reset {
+        seed: Option<u64>,
+    },
+    SetAutomatic(bool),
+    SetDepth(u8),
+    /// Takes back the last move
+    Undo,
+    // the rest are handled by the settings window
+    /// Moves through the loaded recording
+    StepReplay(isize),
+    ToggleRecording,
+    /// Opens a file dialog to pick a recording to replay
+    LoadRecording,
+    CopyPosition,
+    PastePosition,
+    EditPosition,
+}
+
+fn setup(mut commands: Commands, board: Option<Res<BoardResource>>, mut rng: ResMut<GameRng>) {
+    commands.spawn(Camera2dBundle::default());
+    // a resumed game is already on the board
+    if board.is_none() {
+        commands.insert_resource(BoardResource(new_board(&mut rng)));
+    }
+}
+
+fn new_board(rng: &mut GameRng) -> Board {
+    let game = Game::new(rng.seed);
+    rng.placed = game.placed;
+    game.board
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+struct MoveTimer(f32);
+
+/// The players that can move on the live board
+#[derive(Resource)]
+struct Players {
+    keyboard: KeyboardPlayer,
+    search: SearchPlayer,
+    policy: Option<PolicyPlayer>,
+    engine: Option<ExternalPlayer>,
+    // the board a background search was started from, and its result
+    pending: Option<(Board, Task<Option<Swipe>>)>,
+}
+
+impl Default for Players {
+    fn default() -> Self {
+        let ui_settings = UiSettings::default();
+        Self {
+            keyboard: KeyboardPlayer::default(),
+            search: SearchPlayer::new(ui_settings.strategy, ui_settings.depth),
+            policy: None,
+            engine: None,
+            pending: None,
+        }
+    }
+}
+
+impl Players {
+    fn automatic(&mut self, player: AutoPlayer) -> Option<&mut dyn Player> {
+        match player {
+            AutoPlayer::Search => Some(&mut self.search),
+            AutoPlayer::Policy => self.policy.as_mut().map(|policy| policy as &mut dyn Player),
+            AutoPlayer::Engine => self.engine.as_mut().map(|engine| engine as &mut dyn Player),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_commands(
+    mut game_commands: EventReader<GameCommand>,
+    mut players: ResMut<Players>,
+    mut board: ResMut<BoardResource>,
+    mut rng: ResMut<GameRng>,
+    mut metadata: ResMut<GameMetadata>,
+    mut mode: ResMut<GameMode>,
+    mut ui_settings: ResMut<UiSettings>,
+    mut events: EventWriter<UpdateBoardEvent>,
+    mut record_event: EventWriter<RecordEvent>,
+) {
+    for command in game_commands.iter() {
+        match *command {
+            GameCommand::Swipe(swipe) => {
+                if *mode == GameMode::Play {
+                    players.keyboard.press(swipe);
+                }
+            }
+            GameCommand::Reset { seed } => {
+                *rng = seed.map(GameRng::new).unwrap_or_default();
+                board.0 = new_board(&mut rng);
+                *metadata = GameMetadata::default();
+                *mode = GameMode::Play;
+                events.send(UpdateBoardEvent);
+            }
+            GameCommand::SetAutomatic(automatic) => ui_settings.automatic = automatic,
+            GameCommand::SetDepth(depth) => ui_settings.depth = depth,
+            GameCommand::Undo => {
+                if *mode != GameMode::Play {
+                    continue;
+                }
+                if let Some(last) = metadata.history.pop() {
+                    board.0 = last.input;
+                    // the tile placed after the move is placed again if it's replayed
+                    rng.placed = rng.placed.saturating_sub(1);
+                    metadata.human.pop();
+                    record_event.send(RecordEvent::RemoveMove);
+                    events.send(UpdateBoardEvent);
+                }
+            }
+            GameCommand::StepReplay(_)
+            | GameCommand::ToggleRecording
+            | GameCommand::LoadRecording
+            | GameCommand::CopyPosition
+            | GameCommand::PastePosition
+            | GameCommand::EditPosition => {}
+        }
+    }
+}
+
+/// Keeps the automatic players in sync with the settings
+fn update_players(
+    ui_settings: Res<UiSettings>,
+    policy: Res<PolicyResource>,
+    engine: Res<EngineResource>,
+    mut players: ResMut<Players>,
+) {
+    if ui_settings.is_changed() {
+        // building a search can be slow, so only do it when the strategy changes
+        if players.search.strategy != ui_settings.strategy {
+            players.search = SearchPlayer::new(ui_settings.strategy.clone(), ui_settings.depth);
+        }
+        players.search.depth = ui_settings.depth;
+    }
+
+    if policy.is_changed() {
+        players.policy = policy.0.clone().map(PolicyPlayer);
+    }
+
+    if engine.is_changed() {
+        players.engine = engine.0.clone();
+    }
+    if let Some(engine) = &mut players.engine {
+        engine.depth = ui_settings.depth;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update(
+    mut board: ResMut<BoardResource>,
+    mut events: EventWriter<UpdateBoardEvent>,
+    mut record_event: EventWriter<RecordEvent>,
+    mut game_over: EventWriter<GameOver>,
+    mut move_timer: ResMut<MoveTimer>,
+    mut players: ResMut<Players>,
+    mut rng: ResMut<GameRng>,
+    mut metadata: ResMut<GameMetadata>,
+    time: Res<Time>,
+    ui_settings: Res<UiSettings>,
+    mode: Res<GameMode>,
+) {
+    if *mode != GameMode::Play {
+        players.pending = None;
+        return;
+    }
+
+    // positions from the editor can start with the computer to move
+    if !board.player_to_move {
+        let mut game = rng.game(board.0.clone());
+        game.computer_move();
+        rng.placed = game.placed;
+        board.0 = game.board;
+        events.send(UpdateBoardEvent);
+        return;
+    }
+
+    // human player
+    let mut swipe = None;
+    if let Decision::Ready(keyboard_swipe) = players.keyboard.play(&board) {
+        swipe = keyboard_swipe;
+    }
+    let human = swipe.is_some();
+
+    // automatic player, which searches in the background to keep the window responsive
+    let finished = match &mut players.pending {
+        Some((_, task)) => future::block_on(future::poll_once(task)),
+        None => None,
+    };
+    if let Some(result) = finished {
+        let (searched_board, _) = players.pending.take().unwrap();
+        if searched_board == board.0 && swipe.is_none() {
+            swipe = result;
+        }
+    } else if ui_settings.automatic && players.pending.is_none() {
+        move_timer.0 += time.delta_seconds();
+        if move_timer.0 > ui_settings.speed / 1000.0 {
+            move_timer.0 = 0.0;
+
+            if let Some(player) = players.automatic(ui_settings.player) {
+                match player.play(&board) {
+                    Decision::Ready(new_swipe) => swipe = swipe.or(new_swipe),
+                    Decision::Pending(task) => players.pending = Some((board.0.clone(), task)),
+                    Decision::Waiting => {}
+                }
+            }
+        }
+    }
+
+    if let Some(swipe) = swipe {
+        let mut game = rng.game(board.0.clone());
+        if game.play(swipe) {
+            let pair = InoutPair {
+                input: board.0.clone(),
+                output: swipe,
+            };
+            metadata.history.push(pair.clone());
+            metadata.human.push(human);
+            record_event.send(RecordEvent::AddMove(pair));
+
+            if game.is_over() && !metadata.finished {
+                metadata.finished = true;
+                game_over.send(GameOver);
+            }
+            rng.placed = game.placed;
+            board.0 = game.board;
+            events.send(UpdateBoardEvent);
+        }
+    }
+}
+
+/// Runs the game clock while playing, from the first move until the game ends
+fn tick_clock(
+    time: Res<Time>,
+    mode: Res<GameMode>,
+    board: Res<BoardResource>,
+    mut metadata: ResMut<GameMetadata>,
+) {
+    if *mode == GameMode::Play && !metadata.history.is_empty() && !board.get_moves().is_empty() {
+        metadata.duration += time.delta();
+    }
+}
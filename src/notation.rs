@@ -0,0 +1,117 @@
+//! A compact one-line notation for sharing positions.
+//!
+//! Each row is written top to bottom as one base 36 digit per tile exponent
+//! (so `0`-`f` match hex and tiles past 32768 continue with `g` and `h`),
+//! with rows separated by `/`. The side to move follows after a space, `p`
+//! for the player and `c` for the computer:
+//!
+//! `0000/0100/1230/567b p`
+
+use std::{fmt, str::FromStr};
+
+use crate::board::{Board, MAX_EXPONENT};
+
+/// Wraps a board to display and parse it in notation, since `Board` itself
+/// displays as a grid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notation(pub Board);
+
+impl fmt::Display for Notation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..4 {
+            if y > 0 {
+                write!(f, "/")?;
+            }
+            for x in 0..4 {
+                let digit = std::char::from_digit(self.0.data[y][x] as u32, 36).unwrap_or('?');
+                write!(f, "{}", digit)?;
+            }
+        }
+
+        let side = if self.0.player_to_move { 'p' } else { 'c' };
+        write!(f, " {}", side)
+    }
+}
+
+impl FromStr for Notation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let rows = parts.next().ok_or("Empty position")?;
+
+        let mut board = Board::new();
+        board.player_to_move = match parts.next() {
+            Some("p") | None => true,
+            Some("c") => false,
+            Some(side) => return Err(format!("Invalid side to move: {}", side)),
+        };
+        if let Some(extra) = parts.next() {
+            return Err(format!("Unexpected text after the side to move: {}", extra));
+        }
+
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() != 4 {
+            return Err(format!("Expected 4 rows, found {}", rows.len()));
+        }
+        for (y, row) in rows.iter().enumerate() {
+            let digits: Vec<char> = row.chars().collect();
+            if digits.len() != 4 {
+                return Err(format!("Expected 4 tiles in row {}, found {}", y + 1, row));
+            }
+            for (x, digit) in digits.iter().enumerate() {
+                let exp = digit
+                    .to_digit(36)
+                    .ok_or_else(|| format!("Invalid tile: {}", digit))?;
+                if exp > MAX_EXPONENT as u32 {
+                    return Err(format!("Tiles can be at most {}", 1u32 << MAX_EXPONENT));
+                }
+                board.data[y][x] = exp as u8;
+            }
+        }
+
+        Ok(Notation(board))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Board, String> {
+        s.parse::<Notation>().map(|Notation(board)| board)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut board = Board::new();
+        board.data[0][1] = 1;
+        board.data[2][3] = 11;
+        board.data[3][0] = MAX_EXPONENT;
+        board.player_to_move = false;
+
+        let text = Notation(board.clone()).to_string();
+        assert_eq!(text, "0100/0000/000b/h000 c");
+        assert_eq!(parse(&text), Ok(board));
+    }
+
+    #[test]
+    fn side_to_move_defaults_to_player() {
+        let board = parse("0000/0100/1230/567b").unwrap();
+        assert!(board.player_to_move);
+        assert_eq!(board.data[3][3], 11);
+    }
+
+    #[test]
+    fn rejects_invalid_positions() {
+        // past the largest tile
+        assert!(parse("i000/0000/0000/0000 p").is_err());
+        assert!(parse("z000/0000/0000/0000 p").is_err());
+        assert!(parse("0000/0000/0000/0000 p extra").is_err());
+        assert!(parse("0000/0000/0000/0000 x").is_err());
+        assert!(parse("0000/0000/0000 p").is_err());
+        assert!(parse("00000/0000/0000/0000 p").is_err());
+        assert!(parse("000!/0000/0000/0000 p").is_err());
+        assert!(parse("").is_err());
+    }
+}
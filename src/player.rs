@@ -0,0 +1,400 @@
+//! Everything that can choose swipes, and the headless runners that drive
+//! games with them.
+
+use bevy::{
+    prelude::Resource,
+    tasks::{AsyncComputeTaskPool, Task, TaskPool},
+};
+use clap::Args;
+use futures_lite::future;
+use rand::seq::SliceRandom;
+use std::{
+    ffi::OsStr,
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    board::{Board, Moves, Swipe},
+    engine::Engine,
+    game::Game,
+    policy::Policy,
+    record::load_recording,
+    strategy::{Search, Strategy},
+};
+
+pub enum Decision {
+    /// The player chose a swipe, or `None` if they have given up
+    Ready(Option<Swipe>),
+    /// The player hasn't decided yet and should be asked again later
+    Waiting,
+    /// The swipe is being chosen in the background
+    Pending(Task<Option<Swipe>>),
+}
+
+pub trait Player: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Called before each game, for players that keep state between moves
+    fn new_game(&mut self) {}
+
+    fn play(&mut self, board: &Board) -> Decision;
+}
+
+/// Plays swipes as they are pressed
+#[derive(Default)]
+pub struct KeyboardPlayer {
+    queued: Option<Swipe>,
+}
+
+impl KeyboardPlayer {
+    pub fn press(&mut self, swipe: Swipe) {
+        self.queued = Some(swipe);
+    }
+}
+
+impl Player for KeyboardPlayer {
+    fn name(&self) -> String {
+        "keyboard".to_string()
+    }
+
+    fn play(&mut self, _: &Board) -> Decision {
+        match self.queued.take() {
+            Some(swipe) => Decision::Ready(Some(swipe)),
+            None => Decision::Waiting,
+        }
+    }
+}
+
+/// Runs a search strategy in the background
+pub struct SearchPlayer {
+    search: Arc<Mutex<Search>>,
+    pub strategy: Strategy,
+    pub depth: u8,
+}
+
+impl SearchPlayer {
+    pub fn new(strategy: Strategy, depth: u8) -> Self {
+        Self {
+            search: Arc::new(Mutex::new(Search::new(strategy.clone()))),
+            strategy,
+            depth,
+        }
+    }
+}
+
+impl Player for SearchPlayer {
+    fn name(&self) -> String {
+        format!("{:?} depth {}", self.strategy, self.depth)
+    }
+
+    fn play(&mut self, board: &Board) -> Decision {
+        let search = self.search.clone();
+        let board = board.clone();
+        let depth = self.depth;
+
+        let thread_pool = AsyncComputeTaskPool::get();
+        Decision::Pending(
+            thread_pool.spawn(async move { search.lock().unwrap().choose_move(&board, depth) }),
+        )
+    }
+}
+
+pub struct RandomPlayer;
+
+impl Player for RandomPlayer {
+    fn name(&self) -> String {
+        "random".to_string()
+    }
+
+    fn play(&mut self, board: &Board) -> Decision {
+        let swipes: Vec<Swipe> = board
+            .get_moves()
+            .into_iter()
+            .filter_map(|moves| match moves {
+                Moves::Player(swipe) => Some(swipe),
+                Moves::Computer(_) => None,
+            })
+            .collect();
+        Decision::Ready(swipes.choose(&mut rand::thread_rng()).copied())
+    }
+}
+
+pub struct PolicyPlayer(pub Policy);
+
+impl Player for PolicyPlayer {
+    fn name(&self) -> String {
+        "policy".to_string()
+    }
+
+    fn play(&mut self, board: &Board) -> Decision {
+        Decision::Ready(self.0.choose(board))
+    }
+}
+
+/// Replays the swipes from a recording in order
+pub struct PlaybackPlayer {
+    swipes: Vec<Swipe>,
+    next: usize,
+}
+
+impl PlaybackPlayer {
+    pub fn new(swipes: Vec<Swipe>) -> Self {
+        Self { swipes, next: 0 }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let recording = load_recording(&std::fs::read(path)?)?;
+        Ok(Self::new(
+            recording.into_iter().map(|pair| pair.output).collect(),
+        ))
+    }
+}
+
+impl Player for PlaybackPlayer {
+    fn name(&self) -> String {
+        "playback".to_string()
+    }
+
+    fn play(&mut self, _: &Board) -> Decision {
+        let swipe = self.swipes.get(self.next).copied();
+        self.next += 1;
+        Decision::Ready(swipe)
+    }
+}
+
+/// Plays with an engine running in another process, see [`crate::engine`]
+#[derive(Clone)]
+pub struct ExternalPlayer {
+    engine: Arc<Mutex<Engine>>,
+    name: String,
+    pub movetime: Option<Duration>,
+    pub depth: u8,
+}
+
+impl ExternalPlayer {
+    /// Launches the engine and sets its options, given as `(name, value)` pairs
+    pub fn spawn(
+        program: impl AsRef<OsStr>,
+        args: &[&str],
+        options: &[(&str, &str)],
+        depth: u8,
+        movetime: Option<Duration>,
+    ) -> io::Result<Self> {
+        let mut engine = Engine::spawn(program, args)?;
+        for (name, value) in options {
+            engine.set_option(name, value)?;
+        }
+        engine.is_ready()?;
+
+        Ok(Self {
+            name: engine.name.clone(),
+            engine: Arc::new(Mutex::new(engine)),
+            movetime,
+            depth,
+        })
+    }
+}
+
+impl Player for ExternalPlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) {
+        if let Err(error) = self.engine.lock().unwrap().new_game() {
+            println!("External player failed: {}", error);
+        }
+    }
+
+    fn play(&mut self, board: &Board) -> Decision {
+        let engine = self.engine.clone();
+        let board = board.clone();
+        let movetime = self.movetime;
+        let depth = self.depth;
+
+        let thread_pool = AsyncComputeTaskPool::get();
+        Decision::Pending(thread_pool.spawn(async move {
+            match engine
+                .lock()
+                .unwrap()
+                .best_move(&board, movetime, Some(depth))
+            {
+                Ok(swipe) => swipe,
+                Err(error) => {
+                    println!("External player failed: {}", error);
+                    None
+                }
+            }
+        }))
+    }
+}
+
+/// The engine loaded in the window, if any
+#[derive(Resource, Default)]
+pub struct EngineResource(pub Option<ExternalPlayer>);
+
+/// Creates a player from a short description, as used on the command line:
+/// `random`, `policy=<file>`, `playback=<file>`, `external=<command>`, or a
+/// strategy name (`negamax`, `parallel`, `mcts`, `expectimax`). Engine options
+/// can follow an external command as `;<name>=<value>` pairs.
+pub fn from_spec(spec: &str, depth: u8, movetime: Option<Duration>) -> io::Result<Box<dyn Player>> {
+    let (kind, argument) = spec.split_once('=').unwrap_or((spec, ""));
+    let player: Box<dyn Player> = match kind {
+        "random" => Box::new(RandomPlayer),
+        "policy" => Box::new(PolicyPlayer(Policy::load(argument.as_ref())?)),
+        "playback" => Box::new(PlaybackPlayer::load(argument.as_ref())?),
+        "external" => {
+            let mut parts = argument.split(';');
+            let mut command = parts.next().unwrap_or_default().split_whitespace();
+            let program = command.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Empty engine command")
+            })?;
+            let args: Vec<&str> = command.collect();
+            let options: Vec<(&str, &str)> =
+                parts.filter_map(|option| option.split_once('=')).collect();
+            Box::new(ExternalPlayer::spawn(
+                program, &args, &options, depth, movetime,
+            )?)
+        }
+        strategy => match strategy.parse() {
+            Ok(strategy) => Box::new(SearchPlayer::new(strategy, depth)),
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidInput, error)),
+        },
+    };
+    Ok(player)
+}
+
+pub struct GameResult {
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: u32,
+}
+
+/// Plays until the game is over or the player gives up
+pub fn run_game(player: &mut dyn Player, game: &mut Game) -> GameResult {
+    AsyncComputeTaskPool::init(TaskPool::default);
+
+    player.new_game();
+    game.computer_move();
+    while !game.is_over() {
+        let swipe = match player.play(game) {
+            Decision::Ready(swipe) => swipe,
+            Decision::Pending(task) => future::block_on(task),
+            // nobody is going to answer a waiting player here
+            Decision::Waiting => None,
+        };
+
+        match swipe {
+            Some(swipe) if game.play(swipe) => {}
+            _ => break,
+        }
+    }
+
+    GameResult {
+        score: game.score(),
+        max_tile: game.max_tile(),
+        moves: game.moves,
+    }
+}
+
+/// Plays every player on the same seeded games and prints their results
+pub fn tournament(players: &mut [Box<dyn Player>], games: usize, seed: u64) {
+    println!(
+        "{:<40} {:>10} {:>10} {:>10} {:>10}",
+        "player", "avg score", "best", "avg moves", "best tile"
+    );
+
+    for player in players.iter_mut() {
+        let results: Vec<GameResult> = (0..games as u64)
+            .map(|i| run_game(player.as_mut(), &mut Game::new(seed.wrapping_add(i))))
+            .collect();
+
+        let total_score: u64 = results.iter().map(|result| result.score as u64).sum();
+        let total_moves: u64 = results.iter().map(|result| result.moves as u64).sum();
+        println!(
+            "{:<40} {:>10} {:>10} {:>10} {:>10}",
+            player.name(),
+            total_score / games.max(1) as u64,
+            results.iter().map(|result| result.score).max().unwrap_or(0),
+            total_moves / games.max(1) as u64,
+            results
+                .iter()
+                .map(|result| result.max_tile)
+                .max()
+                .unwrap_or(0),
+        );
+    }
+}
+
+/// Options for the `tournament` subcommand
+#[derive(Args)]
+pub struct TournamentArgs {
+    /// Players to compare, see `from_spec`
+    #[arg(required = true)]
+    players: Vec<String>,
+    /// Games per player
+    #[arg(long, default_value_t = 10)]
+    games: usize,
+    /// Time limit per move for external engines, in milliseconds
+    #[arg(long)]
+    movetime: Option<u64>,
+}
+
+pub fn run_tournament(args: TournamentArgs, depth: u8, seed: u64) -> io::Result<()> {
+    let movetime = args.movetime.map(Duration::from_millis);
+    let mut players = args
+        .players
+        .iter()
+        .map(|spec| from_spec(spec, depth, movetime))
+        .collect::<io::Result<Vec<_>>>()?;
+    tournament(&mut players, args.games, seed);
+    Ok(())
+}
+
+/// Options for the `simulate` subcommand
+#[derive(Args)]
+pub struct SimulateArgs {
+    /// Number of games to play
+    #[arg(long, default_value_t = 10)]
+    games: usize,
+    /// Who plays, see `from_spec`. Defaults to the search from `--strategy`.
+    #[arg(long)]
+    player: Option<String>,
+    /// Time limit per move for external engines, in milliseconds
+    #[arg(long)]
+    movetime: Option<u64>,
+}
+
+/// Plays games one after another, printing each result as it finishes
+pub fn simulate(args: SimulateArgs, strategy: Strategy, depth: u8, seed: u64) -> io::Result<()> {
+    let mut player = match &args.player {
+        Some(spec) => from_spec(spec, depth, args.movetime.map(Duration::from_millis))?,
+        None => Box::new(SearchPlayer::new(strategy, depth)),
+    };
+
+    let mut total_score = 0;
+    for i in 0..args.games as u64 {
+        let game_seed = seed.wrapping_add(i);
+        let result = run_game(player.as_mut(), &mut Game::new(game_seed));
+        total_score += result.score as u64;
+        println!(
+            "game {} (seed {}): score {}, best tile {}, {} moves",
+            i + 1,
+            game_seed,
+            result.score,
+            result.max_tile,
+            result.moves
+        );
+    }
+
+    println!(
+        "{}: average score {} over {} games",
+        player.name(),
+        total_score / args.games.max(1) as u64,
+        args.games
+    );
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! A behavior-cloning policy learned from recordings.
+//!
+//! The policy is a multinomial logistic regression over a one-hot encoding of
+//! each tile's exponent, trained with plain stochastic gradient descent.
+
+use bevy::prelude::*;
+use clap::Args;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::{Board, Moves, Swipe},
+    dataset::Dataset,
+    record::InoutPair,
+};
+
+// exponents above this share the last feature
+const EXPONENTS: usize = 18;
+const FEATURES: usize = 16 * EXPONENTS;
+
+const MAGIC: &[u8; 4] = b"tfpl";
+
+#[derive(Resource, Default)]
+pub struct PolicyResource(pub Option<Policy>);
+
+#[derive(Clone)]
+pub struct Policy {
+    weights: Vec<f32>,
+    bias: [f32; 4],
+}
+
+pub struct TrainOptions {
+    pub epochs: usize,
+    pub learning_rate: f32,
+    pub seed: u64,
+}
+
+impl Default for TrainOptions {
+    fn default() -> Self {
+        Self {
+            epochs: 10,
+            learning_rate: 0.05,
+            seed: 0,
+        }
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self {
+            weights: vec![0.0; 4 * FEATURES],
+            bias: [0.0; 4],
+        }
+    }
+
+    fn features(board: &Board) -> [usize; 16] {
+        let mut features = [0; 16];
+        for (i, exp) in board.to_exponents().into_iter().enumerate() {
+            features[i] = i * EXPONENTS + (exp as usize).min(EXPONENTS - 1);
+        }
+        features
+    }
+
+    fn probabilities(&self, features: &[usize; 16]) -> [f32; 4] {
+        let mut logits = self.bias;
+        for (class, logit) in logits.iter_mut().enumerate() {
+            for feature in features {
+                *logit += self.weights[class * FEATURES + feature];
+            }
+        }
+
+        let max = logits.iter().cloned().fold(f32::MIN, f32::max);
+        let mut total = 0.0;
+        for logit in logits.iter_mut() {
+            *logit = (*logit - max).exp();
+            total += *logit;
+        }
+        logits.map(|logit| logit / total)
+    }
+
+    /// The probability of each swipe, indexed by `Swipe::index`
+    pub fn predict(&self, board: &Board) -> [f32; 4] {
+        self.probabilities(&Self::features(board))
+    }
+
+    /// The most likely legal swipe
+    pub fn choose(&self, board: &Board) -> Option<Swipe> {
+        let probabilities = self.predict(board);
+        board
+            .get_moves()
+            .into_iter()
+            .filter_map(|moves| match moves {
+                Moves::Player(swipe) => Some(swipe),
+                Moves::Computer(_) => None,
+            })
+            .max_by(|a, b| probabilities[a.index()].total_cmp(&probabilities[b.index()]))
+    }
+
+    pub fn train_epoch(&mut self, data: &[InoutPair<Board, Swipe>], learning_rate: f32) {
+        for pair in data {
+            let features = Self::features(&pair.input);
+            let probabilities = self.probabilities(&features);
+
+            for (class, probability) in probabilities.iter().enumerate() {
+                let target = if class == pair.output.index() {
+                    1.0
+                } else {
+                    0.0
+                };
+                let step = learning_rate * (probability - target);
+
+                self.bias[class] -= step;
+                for feature in features {
+                    self.weights[class * FEATURES + feature] -= step;
+                }
+            }
+        }
+    }
+
+    /// The fraction of positions where the policy picks the recorded swipe
+    pub fn accuracy(&self, data: &[InoutPair<Board, Swipe>]) -> f32 {
+        let correct = data
+            .iter()
+            .filter(|pair| self.choose(&pair.input) == Some(pair.output))
+            .count();
+        correct as f32 / data.len().max(1) as f32
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut output = MAGIC.to_vec();
+        for value in self.bias.iter().chain(self.weights.iter()) {
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+        std::fs::write(path, output)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let input = std::fs::read(path)?;
+        if !input.starts_with(MAGIC) || input.len() != MAGIC.len() + 4 * (4 + 4 * FEATURES) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a policy file",
+            ));
+        }
+
+        let mut values = input[MAGIC.len()..]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()));
+
+        let mut policy = Policy::new();
+        for value in policy.bias.iter_mut().chain(policy.weights.iter_mut()) {
+            *value = values.next().unwrap();
+        }
+        Ok(policy)
+    }
+}
+
+/// Options for the `train` subcommand. The shuffling seed comes from `--seed`.
+#[derive(Args)]
+pub struct TrainArgs {
+    /// Recordings to learn from
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Where to save the model
+    #[arg(short, long)]
+    output: PathBuf,
+    #[arg(long, default_value_t = 10)]
+    epochs: usize,
+    #[arg(long, default_value_t = 0.05)]
+    learning_rate: f32,
+    /// Fraction of positions held out to measure accuracy
+    #[arg(long, default_value_t = 0.1)]
+    validation: f32,
+    /// Train on every rotation and reflection of each position
+    #[arg(long)]
+    augment: bool,
+}
+
+/// Trains a policy and saves it
+pub fn run(args: TrainArgs, seed: u64) -> io::Result<()> {
+    let TrainArgs {
+        inputs,
+        output,
+        epochs,
+        learning_rate,
+        validation: validation_fraction,
+        augment,
+    } = args;
+    let options = TrainOptions {
+        epochs,
+        learning_rate,
+        seed,
+    };
+
+    let mut dataset = Dataset::default();
+    for input in inputs {
+        dataset.merge(Dataset::load(&input)?);
+    }
+    dataset.dedupe();
+    dataset.shuffle(options.seed);
+
+    let (mut train, validation, _) = dataset.split(1.0 - validation_fraction, validation_fraction);
+    if augment {
+        train.augment();
+    }
+    println!(
+        "Training on {} samples, validating on {}",
+        train.moves.len(),
+        validation.moves.len()
+    );
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut policy = Policy::new();
+    for epoch in 0..options.epochs {
+        train.moves.shuffle(&mut rng);
+        policy.train_epoch(&train.moves, options.learning_rate);
+
+        println!(
+            "epoch {}: train accuracy {:.1}%, validation accuracy {:.1}%",
+            epoch + 1,
+            100.0 * policy.accuracy(&train.moves),
+            100.0 * policy.accuracy(&validation.moves)
+        );
+    }
+
+    policy.save(&output)?;
+    println!("Saved policy to {}", output.display());
+    Ok(())
+}
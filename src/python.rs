@@ -0,0 +1,331 @@
+//! Python bindings, enabled with the `python` feature. Build them into the
+//! current virtualenv with `maturin develop --release`, which builds the
+//! library as a `cdylib` so plain cargo builds don't have to.
+//!
+//! ```python
+//! import twenty_forty_eight as tfe
+//!
+//! env = tfe.Env(tfe.Rewards(survival=1.0))
+//! observation = env.reset(seed=0)
+//! observation, reward, done, info = env.step(tfe.LEFT)
+//! ```
+
+use numpy::{PyArray1, PyArray2, PyReadonlyArray1};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    board::{Board, Moves, Swipe, Symmetry},
+    env::{Env, Observation, RewardConfig, Step, VecEnv},
+    notation::Notation,
+    record::load_recording,
+};
+
+/// A swipe given either by index or by name
+#[derive(FromPyObject)]
+enum SwipeArg {
+    Index(usize),
+    Name(String),
+}
+
+impl SwipeArg {
+    fn swipe(self) -> PyResult<Swipe> {
+        match self {
+            SwipeArg::Index(index) => Swipe::from_index(index)
+                .ok_or_else(|| PyValueError::new_err(format!("Invalid swipe index: {}", index))),
+            SwipeArg::Name(name) => name.parse().map_err(PyValueError::new_err),
+        }
+    }
+}
+
+#[pyclass(name = "Board")]
+#[derive(Clone)]
+struct PyBoard(Board);
+
+#[pymethods]
+impl PyBoard {
+    /// An empty board, or one made from 16 row-major tile exponents
+    #[new]
+    #[pyo3(signature = (exponents = None))]
+    fn new(exponents: Option<[u8; 16]>) -> Self {
+        match exponents {
+            Some(exponents) => PyBoard(Board::from_exponents(exponents)),
+            None => PyBoard(Board::new()),
+        }
+    }
+
+    #[staticmethod]
+    fn from_notation(notation: &str) -> PyResult<Self> {
+        let Notation(board) = notation.parse().map_err(PyValueError::new_err)?;
+        Ok(PyBoard(board))
+    }
+
+    fn notation(&self) -> String {
+        Notation(self.0.clone()).to_string()
+    }
+
+    fn exponents<'py>(&self, py: Python<'py>) -> &'py PyArray1<u8> {
+        PyArray1::from_slice(py, &self.0.to_exponents())
+    }
+
+    #[getter]
+    fn player_to_move(&self) -> bool {
+        self.0.player_to_move
+    }
+
+    /// Returns true if the board changed, after which the computer is to move
+    fn swipe(&mut self, swipe: SwipeArg) -> PyResult<bool> {
+        let mut board = self.0.clone();
+        let changed = board.swipe(swipe.swipe()?);
+        if changed {
+            self.0 = board;
+        }
+        Ok(changed)
+    }
+
+    /// Places the computers tile, like the game does after every swipe
+    #[pyo3(signature = (seed = None))]
+    fn computer_move(&mut self, seed: Option<u64>) {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        self.0.computer_move_with(&mut rng);
+    }
+
+    /// The names of the swipes that change the board
+    fn legal_moves(&self) -> Vec<String> {
+        self.0
+            .get_moves()
+            .into_iter()
+            .filter_map(|moves| match moves {
+                Moves::Player(swipe) => Some(swipe.to_string()),
+                Moves::Computer(_) => None,
+            })
+            .collect()
+    }
+
+    fn action_mask<'py>(&self, py: Python<'py>) -> &'py PyArray1<bool> {
+        let mut mask = [false; 4];
+        for moves in self.0.get_moves() {
+            if let Moves::Player(swipe) = moves {
+                mask[swipe.index()] = true;
+            }
+        }
+        PyArray1::from_slice(py, &mask)
+    }
+
+    fn score(&self) -> u32 {
+        self.0.score()
+    }
+
+    /// The board under all 8 rotations and reflections, starting with itself
+    fn symmetries(&self) -> Vec<PyBoard> {
+        Symmetry::ALL
+            .iter()
+            .map(|symmetry| PyBoard(self.0.transformed(*symmetry)))
+            .collect()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Board.from_notation('{}')", self.notation())
+    }
+}
+
+/// Reward shaping for the environments, see `RewardConfig`
+#[pyclass(name = "Rewards")]
+#[derive(Clone)]
+struct PyRewards(RewardConfig);
+
+#[pymethods]
+impl PyRewards {
+    #[new]
+    #[pyo3(signature = (merge = 1.0, survival = 0.0, milestone = 0.0, milestone_exponent = 9, invalid = -1.0, game_over = 0.0))]
+    fn new(
+        merge: f32,
+        survival: f32,
+        milestone: f32,
+        milestone_exponent: u8,
+        invalid: f32,
+        game_over: f32,
+    ) -> Self {
+        PyRewards(RewardConfig {
+            merge,
+            survival,
+            milestone,
+            milestone_exponent,
+            invalid,
+            game_over,
+        })
+    }
+}
+
+#[pyclass(name = "Env")]
+struct PyEnv(Env);
+
+#[pymethods]
+impl PyEnv {
+    #[new]
+    #[pyo3(signature = (rewards = None))]
+    fn new(rewards: Option<PyRewards>) -> Self {
+        PyEnv(Env::new(
+            rewards.map(|rewards| rewards.0).unwrap_or_default(),
+        ))
+    }
+
+    #[pyo3(signature = (seed = None))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: Option<u64>) -> &'py PyArray1<u8> {
+        let observation = self.0.reset(seed.unwrap_or_else(rand::random));
+        PyArray1::from_slice(py, &observation)
+    }
+
+    /// Returns `(observation, reward, done, info)`
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: usize,
+    ) -> PyResult<(&'py PyArray1<u8>, f32, bool, &'py PyDict)> {
+        let step = self.0.step(action);
+
+        let info = PyDict::new(py);
+        info.set_item("score", step.info.score)?;
+        info.set_item("max_tile", step.info.max_tile)?;
+        info.set_item("moves", step.info.moves)?;
+        info.set_item("valid", step.info.valid)?;
+        info.set_item("action_mask", step.info.action_mask.to_vec())?;
+
+        let observation = PyArray1::from_slice(py, &step.observation);
+        Ok((observation, step.reward, step.done, info))
+    }
+
+    fn action_mask<'py>(&self, py: Python<'py>) -> &'py PyArray1<bool> {
+        PyArray1::from_slice(py, &self.0.action_mask())
+    }
+
+    fn board(&self) -> PyBoard {
+        PyBoard(self.0.game().board.clone())
+    }
+}
+
+/// Steps many environments at once, in parallel and without holding the GIL.
+/// Finished games are reset automatically.
+#[pyclass(name = "VecEnv")]
+struct PyVecEnv(VecEnv);
+
+#[pymethods]
+impl PyVecEnv {
+    #[new]
+    #[pyo3(signature = (count, rewards = None))]
+    fn new(count: usize, rewards: Option<PyRewards>) -> Self {
+        PyVecEnv(VecEnv::new(
+            count,
+            rewards.map(|rewards| rewards.0).unwrap_or_default(),
+        ))
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    #[pyo3(signature = (seed = None))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: Option<u64>) -> PyResult<&'py PyArray2<u8>> {
+        let observations = self.0.reset(seed.unwrap_or_else(rand::random));
+        observation_array(py, &observations)
+    }
+
+    /// Returns `(observations, rewards, dones, info)`, where each entry in
+    /// info is an array with one value per environment
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        actions: PyReadonlyArray1<i64>,
+    ) -> PyResult<(
+        &'py PyArray2<u8>,
+        &'py PyArray1<f32>,
+        &'py PyArray1<bool>,
+        &'py PyDict,
+    )> {
+        let actions: Vec<usize> = actions
+            .as_array()
+            .iter()
+            .map(|&action| usize::try_from(action).unwrap_or(usize::MAX))
+            .collect();
+        if actions.len() != self.0.len() {
+            return Err(PyValueError::new_err(format!(
+                "Expected {} actions, got {}",
+                self.0.len(),
+                actions.len()
+            )));
+        }
+
+        let vec_env = &mut self.0;
+        let steps = py.allow_threads(|| vec_env.step(&actions));
+
+        let observations: Vec<Observation> = steps.iter().map(|step| step.observation).collect();
+        let column = |value: fn(&Step) -> u32| PyArray1::from_iter(py, steps.iter().map(value));
+
+        let info = PyDict::new(py);
+        info.set_item("score", column(|step| step.info.score))?;
+        info.set_item("max_tile", column(|step| step.info.max_tile))?;
+        info.set_item("moves", column(|step| step.info.moves))?;
+        info.set_item(
+            "valid",
+            PyArray1::from_iter(py, steps.iter().map(|step| step.info.valid)),
+        )?;
+
+        Ok((
+            observation_array(py, &observations)?,
+            PyArray1::from_iter(py, steps.iter().map(|step| step.reward)),
+            PyArray1::from_iter(py, steps.iter().map(|step| step.done)),
+            info,
+        ))
+    }
+
+    fn action_masks<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray2<bool>> {
+        let masks: Vec<bool> = self.0.action_masks().into_iter().flatten().collect();
+        PyArray1::from_vec(py, masks).reshape([self.0.len(), 4])
+    }
+}
+
+fn observation_array<'py>(
+    py: Python<'py>,
+    observations: &[Observation],
+) -> PyResult<&'py PyArray2<u8>> {
+    let flat: Vec<u8> = observations.iter().flatten().copied().collect();
+    PyArray1::from_vec(py, flat).reshape([observations.len(), 16])
+}
+
+/// Reads a `.tfer` recording as `(boards, actions)`, with boards as an
+/// `(N, 16)` array of row-major exponents and actions as swipe indices
+#[pyfunction]
+fn read_recording<'py>(
+    py: Python<'py>,
+    path: &str,
+) -> PyResult<(&'py PyArray2<u8>, &'py PyArray1<u8>)> {
+    let recording = load_recording(&std::fs::read(path)?)?;
+    let boards: Vec<Observation> = recording
+        .iter()
+        .map(|pair| pair.input.to_exponents())
+        .collect();
+    let actions = PyArray1::from_iter(py, recording.iter().map(|pair| pair.output.index() as u8));
+    Ok((observation_array(py, &boards)?, actions))
+}
+
+#[pymodule]
+fn twenty_forty_eight(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyBoard>()?;
+    module.add_class::<PyRewards>()?;
+    module.add_class::<PyEnv>()?;
+    module.add_class::<PyVecEnv>()?;
+    module.add_function(wrap_pyfunction!(read_recording, module)?)?;
+
+    for swipe in Swipe::ALL {
+        module.add(swipe.to_string().to_uppercase().as_str(), swipe.index())?;
+    }
+    Ok(())
+}
@@ -1,8 +1,11 @@
 use bevy::prelude::*;
 use futures_lite::future;
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use crate::board::{Board, Swipe};
+use crate::board::{Board, Swipe, MAX_EXPONENT};
 
 pub struct RecordPlugin;
 
@@ -14,6 +17,7 @@ impl Plugin for RecordPlugin {
                 save_location: "".into(),
                 move_stack: Vec::new(),
             })
+            .init_resource::<GameMetadata>()
             .add_systems(Update, record_system);
     }
 }
@@ -31,12 +35,100 @@ pub struct RecordInfo {
     move_stack: Vec<InoutPair<Board, Swipe>>,
 }
 
+/// Information about the current game that is saved next to its recording
+#[derive(Resource, Default, Clone)]
+pub struct GameMetadata {
+    pub forked_from: Option<ForkOrigin>,
+    /// The moves played so far, whether or not they are being recorded.
+    /// Saved as a recording rather than in the metadata file.
+    pub history: Vec<InoutPair<Board, Swipe>>,
+    /// Whether each move in `history` was made from the keyboard
+    pub human: Vec<bool>,
+    /// Set once the game has ended and been added to the statistics, so
+    /// undoing the last move and ending it again doesn't count it twice
+    pub finished: bool,
+    /// Time spent playing, which doesn't count replaying or editing
+    pub duration: Duration,
+}
+
+/// The recording position a game was branched from
+#[derive(Clone)]
+pub struct ForkOrigin {
+    pub file: PathBuf,
+    pub index: usize,
+}
+
+impl GameMetadata {
+    /// How many of the moves were made from the keyboard
+    pub fn human_moves(&self) -> usize {
+        self.human.iter().filter(|&&human| human).count()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut output = String::new();
+        if let Some(fork) = &self.forked_from {
+            output.push_str(&format!("fork_file={}\n", fork.file.display()));
+            output.push_str(&format!("fork_index={}\n", fork.index));
+        }
+        let human: String = self
+            .human
+            .iter()
+            .map(|&human| if human { '1' } else { '0' })
+            .collect();
+        output.push_str(&format!("human={}\n", human));
+        output.push_str(&format!("finished={}\n", self.finished));
+        output.push_str(&format!("duration_ms={}\n", self.duration.as_millis()));
+        std::fs::write(path, output)
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let input = std::fs::read_to_string(path)?;
+
+        let mut fork_file = None;
+        let mut fork_index = None;
+        let mut human = Vec::new();
+        let mut finished = false;
+        let mut duration = Duration::ZERO;
+        for line in input.lines() {
+            match line.split_once('=') {
+                Some(("fork_file", value)) => fork_file = Some(PathBuf::from(value)),
+                Some(("fork_index", value)) => fork_index = value.parse().ok(),
+                Some(("human", value)) => human = value.chars().map(|c| c == '1').collect(),
+                Some(("finished", value)) => finished = value == "true",
+                Some(("duration_ms", value)) => {
+                    duration = Duration::from_millis(value.parse().unwrap_or(0))
+                }
+                _ => {}
+            }
+        }
+
+        let forked_from = match (fork_file, fork_index) {
+            (Some(file), Some(index)) => Some(ForkOrigin { file, index }),
+            _ => None,
+        };
+        Ok(Self {
+            forked_from,
+            human,
+            finished,
+            duration,
+            ..default()
+        })
+    }
+}
+
+/// The metadata file stored alongside a recording
+pub fn metadata_path(recording: &Path) -> PathBuf {
+    recording.with_extension("meta")
+}
+
 #[derive(Event)]
 pub enum RecordEvent {
     Start,
     Stop,
     // inital board and correct direction
     AddMove(InoutPair<Board, Swipe>),
+    /// Drops the last move, after it was undone
+    RemoveMove,
 }
 
 #[derive(Component)]
@@ -47,6 +139,7 @@ fn record_system(
     mut file_dialog: Query<(Entity, &mut SelectedFile)>,
     mut record_info: ResMut<RecordInfo>,
     mut record_event: EventReader<RecordEvent>,
+    metadata: Res<GameMetadata>,
 ) {
     for event in record_event.iter() {
         match event {
@@ -68,46 +161,109 @@ fn record_system(
                 println!("Saving recording");
 
                 // save the recorded moves
-                let mut file_output = Vec::new();
-                for i in 0..record_info.move_stack.len() {
-                    let InoutPair { input, output } = &record_info.move_stack[i];
-                    input.serialize(&mut file_output);
-                    output.serialize(&mut file_output);
+                let file_output = serialize_recording(&record_info.move_stack);
+                if let Err(error) = std::fs::write(&record_info.save_location, file_output) {
+                    println!("Failed to save recording: {}", error);
+                    continue;
                 }
 
-                use std::io::Write;
-                let mut file = std::fs::File::create(record_info.save_location.clone()).unwrap();
-                file.write_all(&file_output).unwrap();
+                // only forks and keyboard moves need anything beyond the moves
+                if metadata.forked_from.is_some() || metadata.human_moves() > 0 {
+                    let path = metadata_path(&record_info.save_location);
+                    if let Err(error) = metadata.save(&path) {
+                        println!("Failed to save recording metadata: {}", error);
+                    }
+                }
             }
             RecordEvent::AddMove(inout_pair) => {
                 if record_info.recording {
                     record_info.move_stack.push(inout_pair.clone());
                 }
             }
+            RecordEvent::RemoveMove => {
+                if record_info.recording {
+                    record_info.move_stack.pop();
+                }
+            }
         }
     }
 
     // check for file dialog completion
     for (entity, mut selected_file) in file_dialog.iter_mut() {
         if let Some(result) = future::block_on(future::poll_once(&mut selected_file.0)) {
-            record_info.save_location = result.unwrap();
             commands.entity(entity).despawn();
+            match result {
+                Some(path) => record_info.save_location = path,
+                // cancelling the dialog cancels the recording
+                None => {
+                    record_info.recording = false;
+                    println!("Recording cancelled");
+                }
+            }
         }
     }
 }
 
-pub fn load_board_from_file(file: &[u8], index: usize) -> InoutPair<Board, Swipe> {
+/// Reads the move at `index` of a recording
+pub fn load_board_from_file(file: &[u8], index: usize) -> std::io::Result<InoutPair<Board, Swipe>> {
     // each board is 17 bytes
     let board_index = index * 17;
-    let board_slice = &file[board_index..board_index + 17];
+    let board_slice = file.get(board_index..board_index + 17).ok_or_else(|| {
+        invalid_recording(format!(
+            "no move {} in a recording of {} bytes",
+            index,
+            file.len()
+        ))
+    })?;
 
-    let board = Board::deserialize(board_slice);
-    let direction = Swipe::deserialize(&board_slice[16..17]);
+    let board = Board::deserialize(board_slice)?;
+    let direction = Swipe::deserialize(&board_slice[16..17])?;
 
-    InoutPair {
+    Ok(InoutPair {
         input: board,
         output: direction,
+    })
+}
+
+/// Reads every move of a recording, failing on bad tiles or swipes and on a
+/// length that isn't a whole number of moves
+pub fn load_recording(file: &[u8]) -> std::io::Result<Vec<InoutPair<Board, Swipe>>> {
+    let trailing = file.len() % 17;
+    if trailing != 0 {
+        return Err(invalid_recording(format!(
+            "{} trailing bytes after the last move",
+            trailing
+        )));
+    }
+    (0..file.len() / 17)
+        .map(|index| load_board_from_file(file, index))
+        .collect()
+}
+
+fn invalid_recording(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+pub fn serialize_recording(moves: &[InoutPair<Board, Swipe>]) -> Vec<u8> {
+    let mut file_output = Vec::new();
+    for InoutPair { input, output } in moves {
+        input.serialize(&mut file_output);
+        output.serialize(&mut file_output);
+    }
+    file_output
+}
+
+/// Prints every position of a recording with the swipe that was played,
+/// waiting `delay` between moves
+pub fn replay(path: &Path, delay: Duration) -> std::io::Result<()> {
+    let recording = load_recording(&std::fs::read(path)?)?;
+    for (index, pair) in recording.iter().enumerate() {
+        println!("move {} (score {})", index, pair.input.score());
+        println!("{}swiped {}\n", pair.input, pair.output);
+        std::thread::sleep(delay);
     }
+    println!("{} moves", recording.len());
+    Ok(())
 }
 
 trait Searialize {
@@ -135,35 +291,75 @@ impl Searialize for Swipe {
     }
 }
 
-trait Deserialize {
-    fn deserialize(input: &[u8]) -> Self;
+trait Deserialize: Sized {
+    fn deserialize(input: &[u8]) -> std::io::Result<Self>;
 }
 
 impl Deserialize for Board {
-    fn deserialize(input: &[u8]) -> Self {
+    fn deserialize(input: &[u8]) -> std::io::Result<Self> {
         let mut data = vec![vec![0; 4]; 4];
         let mut i = 0;
         for x in 0..4 {
             for y in 0..4 {
+                if input[i] > MAX_EXPONENT {
+                    return Err(invalid_recording(format!("invalid tile {}", input[i])));
+                }
                 data[y][x] = input[i];
                 i += 1;
             }
         }
-        Board {
+        Ok(Board {
             data,
             player_to_move: true,
-        }
+        })
     }
 }
 
 impl Deserialize for Swipe {
-    fn deserialize(input: &[u8]) -> Self {
+    fn deserialize(input: &[u8]) -> std::io::Result<Self> {
         match input[0] {
-            0 => Swipe::Up,
-            1 => Swipe::Down,
-            2 => Swipe::Left,
-            3 => Swipe::Right,
-            _ => panic!("Invalid direction"),
+            0 => Ok(Swipe::Up),
+            1 => Ok(Swipe::Down),
+            2 => Ok(Swipe::Left),
+            3 => Ok(Swipe::Right),
+            other => Err(invalid_recording(format!("invalid direction {}", other))),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> Vec<u8> {
+        serialize_recording(&[InoutPair {
+            input: Board::from_exponents([3; 16]),
+            output: Swipe::Right,
+        }])
+    }
+
+    #[test]
+    fn round_trip() {
+        let moves = load_recording(&recording()).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].input, Board::from_exponents([3; 16]));
+        assert_eq!(moves[0].output, Swipe::Right);
+    }
+
+    #[test]
+    fn rejects_invalid_recordings() {
+        let invalid = |file: &[u8]| load_recording(file).err().map(|error| error.kind());
+
+        let mut bad_swipe = recording();
+        bad_swipe[16] = 4;
+        assert_eq!(invalid(&bad_swipe), Some(std::io::ErrorKind::InvalidData));
+
+        let mut bad_tile = recording();
+        bad_tile[0] = MAX_EXPONENT + 1;
+        assert_eq!(invalid(&bad_tile), Some(std::io::ErrorKind::InvalidData));
+
+        let mut trailing = recording();
+        trailing.push(0);
+        assert_eq!(invalid(&trailing), Some(std::io::ErrorKind::InvalidData));
+    }
+}
@@ -1,14 +1,22 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, window::PrimaryWindow};
+use std::{collections::VecDeque, time::Duration};
 
-use crate::BoardResource;
+use crate::{
+    board::Pos,
+    record::GameMetadata,
+    theme::{tile_font_size, Themes},
+    ui::{SidePanelWidth, UiSettings},
+    BoardResource,
+};
 
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<UpdateBoardEvent>()
+            .insert_resource(Themes::load())
             .add_systems(Startup, setup)
-            .add_systems(Update, update_board);
+            .add_systems(Update, (update_board, update_clock, scale_layout));
     }
 }
 
@@ -16,44 +24,69 @@ impl Plugin for BoardPlugin {
 pub struct UpdateBoardEvent;
 
 #[derive(Component)]
-struct Tile(u32);
+pub struct Tile(u32);
+
+impl Tile {
+    /// The board position shown by this tile
+    pub fn pos(&self) -> Pos {
+        Pos::new(self.0 as usize % 4, 3 - self.0 as usize / 4)
+    }
+}
 #[derive(Component)]
 struct TileText(u32);
 
+#[derive(Component)]
+struct Background;
+/// The board behind the tiles, which gestures are started on
+#[derive(Component)]
+pub struct BoardBackground;
+
 #[derive(Component)]
 struct ScoreText;
 #[derive(Component)]
 struct TimeText;
+#[derive(Component)]
+struct MovesText;
 
-const fn color_map(exp: u8) -> Color {
-    match exp {
-        0 => Color::rgb(0.80, 0.76, 0.71),
-        1 => Color::rgb(0.93, 0.90, 0.85),
-        2 => Color::rgb(0.93, 0.89, 0.79),
-        3 => Color::rgb(0.95, 0.7, 0.48),
-        4 => Color::rgb(0.96, 0.59, 0.39),
-        5 => Color::rgb(0.97, 0.49, 0.37),
-        6 => Color::rgb(0.97, 0.37, 0.24),
-        7..=11 => Color::rgb(0.93, 0.82, 0.45),
-        _ => Color::rgb(0.4, 0.4, 0.4),
-    }
+/// Moves per second are averaged over this many seconds
+const RATE_WINDOW: f32 = 2.0;
+
+/// When the recent moves were made
+#[derive(Default)]
+struct MoveRate {
+    moves: usize,
+    times: VecDeque<f32>,
 }
 
+/// The width and height of a tile in pixels, before the layout is scaled
+const TILE_SIZE: f32 = 87.5;
+
+/// The size of the header and board together, which is scaled to fit the
+/// window with `LAYOUT_MARGIN` to spare on every side
+const LAYOUT_WIDTH: f32 = 400.0;
+const LAYOUT_HEIGHT: f32 = 520.0;
+const LAYOUT_MARGIN: f32 = 20.0;
+
 fn update_board(
     board: Res<BoardResource>,
+    themes: Res<Themes>,
+    ui_settings: Res<UiSettings>,
     mut update_event: EventReader<UpdateBoardEvent>,
     mut querys: ParamSet<(
         Query<(&Tile, &mut BackgroundColor)>,
         Query<(&TileText, &mut Text)>,
         Query<&mut Text, With<ScoreText>>,
-        Query<&mut Text, With<TimeText>>,
+        Query<&mut Text, Without<TileText>>,
+        Query<&mut BackgroundColor, With<Background>>,
+        Query<&mut BackgroundColor, With<BoardBackground>>,
     )>,
-    time: Res<Time>,
 ) {
+    let theme = themes.get(&ui_settings.theme);
     for _ in update_event.iter() {
         for (tile, mut ui_colour) in querys.p0().iter_mut() {
-            let exp = board.data[3 - tile.0 as usize / 4][tile.0 as usize % 4];
-            *ui_colour = color_map(exp).into();
+            let pos = tile.pos();
+            let exp = board.data[pos.y][pos.x];
+            *ui_colour = Color::from(theme.tile(exp)).into();
         }
 
         for (tile_text, mut text) in querys.p1().iter_mut() {
@@ -65,20 +98,107 @@ fn update_board(
                 (1u32 << exp as u32).to_string()
             };
             text.sections[0].value = string;
-            text.sections[0].style.color = if exp <= 3 {
-                Color::rgb(0.47, 0.44, 0.40)
-            } else {
-                Color::rgb(0.98, 0.96, 0.95)
-            };
+            text.sections[0].style.color = theme.tile_text(exp).into();
+            text.sections[0].style.font_size = tile_font_size(exp, TILE_SIZE);
         }
 
         let mut score_query = querys.p2();
         let mut score_text = score_query.single_mut();
         score_text.sections[0].value = board.score().to_string();
 
-        let mut time_query = querys.p3();
-        let mut time_text = time_query.single_mut();
-        time_text.sections[0].value = format!("{:.2}", time.startup().elapsed().as_secs_f32());
+        for mut text in querys.p3().iter_mut() {
+            text.sections[0].style.color = theme.text.into();
+        }
+        *querys.p4().single_mut() = Color::from(theme.background).into();
+        *querys.p5().single_mut() = Color::from(theme.board).into();
+    }
+}
+
+/// Shows the game clock and move count, and the moves per second during autoplay
+#[allow(clippy::type_complexity)]
+fn update_clock(
+    metadata: Res<GameMetadata>,
+    ui_settings: Res<UiSettings>,
+    time: Res<Time>,
+    mut rate: Local<MoveRate>,
+    mut querys: ParamSet<(
+        Query<&mut Text, With<TimeText>>,
+        Query<&mut Text, With<MovesText>>,
+    )>,
+) {
+    let now = time.elapsed_seconds();
+    let moves = metadata.history.len();
+    if moves < rate.moves {
+        // a new game
+        rate.times.clear();
+    }
+    for _ in rate.moves..moves {
+        rate.times.push_back(now);
+    }
+    rate.moves = moves;
+    while rate
+        .times
+        .front()
+        .is_some_and(|time| now - time > RATE_WINDOW)
+    {
+        rate.times.pop_front();
+    }
+
+    let mut time_query = querys.p0();
+    time_query.single_mut().sections[0].value = format_clock(metadata.duration);
+
+    let mut moves_query = querys.p1();
+    moves_query.single_mut().sections[0].value = if ui_settings.automatic {
+        format!(
+            "{} moves, {:.1} per second",
+            moves,
+            rate.times.len() as f32 / RATE_WINDOW
+        )
+    } else {
+        format!("{} moves", moves)
+    };
+}
+
+/// Scales the whole board to fit the part of the window the settings panel
+/// leaves free, keeping its aspect ratio
+fn scale_layout(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    side_panel: Res<SidePanelWidth>,
+    mut ui_scale: ResMut<UiScale>,
+    mut background: Query<&mut Style, With<Background>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let width = window.width() - side_panel.0;
+    let height = window.height();
+    let scale = (width / (LAYOUT_WIDTH + 2.0 * LAYOUT_MARGIN))
+        .min(height / (LAYOUT_HEIGHT + 2.0 * LAYOUT_MARGIN))
+        .max(0.1);
+
+    if (ui_scale.scale - scale as f64).abs() > 0.001 {
+        ui_scale.scale = scale as f64;
+    }
+
+    // padding is scaled too, so it's divided back out to cover the panel exactly
+    let padding = Val::Px(side_panel.0 / scale);
+    let mut style = background.single_mut();
+    if style.padding.right != padding {
+        style.padding.right = padding;
+    }
+}
+
+fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
     }
 }
 
@@ -99,11 +219,12 @@ fn setup(
             background_color: Color::rgb(0.98, 0.97, 0.94).into(),
             ..default()
         })
+        .insert(Background)
         .with_children(|parent| {
             parent
                 .spawn(NodeBundle {
                     style: Style {
-                        width: Val::Px(400.0),
+                        width: Val::Px(LAYOUT_WIDTH),
                         height: Val::Percent(100.0),
                         flex_direction: FlexDirection::Column,
                         align_items: AlignItems::Center,
@@ -150,11 +271,22 @@ fn setup(
                                 .insert(TimeText);
                         });
 
+                    parent
+                        .spawn(TextBundle::from_section(
+                            "0 moves",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 24.0,
+                                color: Color::rgb(0.47, 0.44, 0.40),
+                            },
+                        ))
+                        .insert(MovesText);
+
                     parent
                         .spawn(NodeBundle {
                             style: Style {
-                                width: Val::Px(400.0),
-                                height: Val::Px(400.0),
+                                width: Val::Px(LAYOUT_WIDTH),
+                                height: Val::Px(LAYOUT_WIDTH),
                                 padding: UiRect {
                                     top: Val::Px(10.0),
                                     left: Val::Px(10.0),
@@ -167,13 +299,14 @@ fn setup(
                             background_color: Color::rgb(0.73, 0.68, 0.63).into(),
                             ..default()
                         })
+                        .insert((BoardBackground, Interaction::default()))
                         .with_children(|parent| {
                             for i in 0..16 {
                                 parent
                                     .spawn(NodeBundle {
                                         style: Style {
-                                            width: Val::Px(87.5),
-                                            height: Val::Px(87.5),
+                                            width: Val::Px(TILE_SIZE),
+                                            height: Val::Px(TILE_SIZE),
                                             margin: UiRect {
                                                 bottom: Val::Px(10.0),
                                                 right: Val::Px(10.0),
@@ -186,7 +319,7 @@ fn setup(
                                         background_color: Color::rgb(0.6, 0.6, 0.6).into(),
                                         ..default()
                                     })
-                                    .insert(Tile(i))
+                                    .insert((Tile(i), Interaction::default()))
                                     .with_children(|parent| {
                                         parent
                                             .spawn(TextBundle::from_section(
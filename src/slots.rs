@@ -0,0 +1,156 @@
+//! Saved games, used for the named save slots and for resuming the last
+//! session. A saved game is a recording of the moves played so far, with its
+//! metadata and the current position stored next to it.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    ffi::OsStr,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::Board,
+    config::config_dir,
+    notation::Notation,
+    record::{load_recording, metadata_path, serialize_recording, GameMetadata},
+    GameRng,
+};
+
+/// A game that can be picked up again where it was left
+#[derive(Clone)]
+pub struct SavedGame {
+    pub board: Board,
+    pub rng: GameRng,
+    pub metadata: GameMetadata,
+    pub saved: DateTime<Local>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PositionFile {
+    /// The board in notation
+    board: String,
+    // TOML integers are signed, so the seed's bits are stored as an i64
+    seed: i64,
+    placed: u32,
+    saved: DateTime<Local>,
+}
+
+/// The file holding the position of the game saved at `recording`
+fn position_path(recording: &Path) -> PathBuf {
+    recording.with_extension("toml")
+}
+
+fn invalid_data(error: impl ToString) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, error.to_string())
+}
+
+impl SavedGame {
+    pub fn new(board: Board, rng: GameRng, metadata: GameMetadata) -> Self {
+        Self {
+            board,
+            rng,
+            metadata,
+            saved: Local::now(),
+        }
+    }
+
+    /// Loads the game saved at the recording `path`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let input = std::fs::read_to_string(position_path(path))?;
+        let position: PositionFile = toml::from_str(&input).map_err(invalid_data)?;
+        let Notation(board) = position.board.parse().map_err(invalid_data)?;
+
+        let mut metadata = GameMetadata::load(&metadata_path(path))?;
+        metadata.history = load_recording(&std::fs::read(path)?)?;
+        metadata.human.resize(metadata.history.len(), false);
+
+        Ok(Self {
+            board,
+            rng: GameRng {
+                seed: position.seed as u64,
+                placed: position.placed,
+            },
+            metadata,
+            saved: position.saved,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let position = PositionFile {
+            board: Notation(self.board.clone()).to_string(),
+            seed: self.rng.seed as i64,
+            placed: self.rng.placed,
+            saved: self.saved,
+        };
+        let output = toml::to_string(&position).map_err(invalid_data)?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serialize_recording(&self.metadata.history))?;
+        self.metadata.save(&metadata_path(path))?;
+        std::fs::write(position_path(path), output)
+    }
+
+    pub fn delete(path: &Path) -> io::Result<()> {
+        std::fs::remove_file(position_path(path))?;
+        std::fs::remove_file(metadata_path(path))?;
+        std::fs::remove_file(path)
+    }
+}
+
+/// A named save slot
+#[derive(Clone)]
+pub struct Slot {
+    pub name: String,
+    pub game: SavedGame,
+}
+
+fn slots_dir() -> io::Result<PathBuf> {
+    Ok(config_dir()?.join("slots"))
+}
+
+/// The recording a slot is saved to. Names are used as file names, so only
+/// letters, digits, spaces, `-` and `_` are allowed.
+pub fn slot_path(name: &str) -> io::Result<PathBuf> {
+    let valid = |c: char| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_';
+    if name.trim().is_empty() || !name.chars().all(valid) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid slot name: {:?}", name),
+        ));
+    }
+    Ok(slots_dir()?.join(name.trim()).with_extension("tfer"))
+}
+
+/// Every saved slot, most recently saved first
+pub fn list_slots() -> io::Result<Vec<Slot>> {
+    let entries = match std::fs::read_dir(slots_dir()?) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+
+    let mut slots = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension() != Some(OsStr::new("tfer")) {
+            continue;
+        }
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        match SavedGame::load(&path) {
+            Ok(game) => slots.push(Slot { name, game }),
+            Err(error) => println!("Skipping save slot {}: {}", name, error),
+        }
+    }
+
+    slots.sort_by_key(|slot| Reverse(slot.game.saved));
+    Ok(slots)
+}
@@ -0,0 +1,222 @@
+//! Statistics over every finished game, kept as one JSON object per line in
+//! the config directory, and the dashboard that shows them.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use chrono::{DateTime, Local};
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoints};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, BufRead, ErrorKind, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    config::config_dir,
+    record::GameMetadata,
+    ui::{AutoPlayer, UiSettings},
+    BoardResource, GameRng,
+};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        let stats = load_stats().unwrap_or_else(|error| {
+            println!("Failed to load statistics: {}", error);
+            Vec::new()
+        });
+        app.add_event::<GameOver>()
+            .insert_resource(Stats(stats))
+            .add_systems(Update, (record_finished_games, stats_ui));
+    }
+}
+
+/// Sent when a move on the live board ends the game
+#[derive(Event)]
+pub struct GameOver;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameRecord {
+    pub finished: DateTime<Local>,
+    pub score: u32,
+    pub max_tile: u32,
+    pub moves: usize,
+    /// Seconds spent playing
+    #[serde(default)]
+    pub duration: f64,
+    pub seed: u64,
+    /// `human`, `search`, `policy`, `engine`, or `mixed` when both the
+    /// keyboard and an automatic player made moves
+    pub player: String,
+    /// The search settings, for games an automatic player had a part in
+    pub strategy: Option<String>,
+    pub depth: Option<u8>,
+}
+
+#[derive(Resource)]
+struct Stats(Vec<GameRecord>);
+
+fn stats_path() -> io::Result<PathBuf> {
+    Ok(config_dir()?.join("stats.jsonl"))
+}
+
+pub fn load_stats() -> io::Result<Vec<GameRecord>> {
+    let file = match std::fs::File::open(stats_path()?) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+
+    // a line cut short by a crash shouldn't lose every other game
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(_) => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        println!("Skipped {} unreadable lines in the statistics", skipped);
+    }
+    Ok(records)
+}
+
+fn append_stats(record: &GameRecord) -> io::Result<()> {
+    std::fs::create_dir_all(config_dir()?)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(stats_path()?)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+fn record_finished_games(
+    mut game_over: EventReader<GameOver>,
+    mut stats: ResMut<Stats>,
+    board: Res<BoardResource>,
+    metadata: Res<GameMetadata>,
+    rng: Res<GameRng>,
+    ui_settings: Res<UiSettings>,
+) {
+    for _ in game_over.iter() {
+        let moves = metadata.history.len();
+        let human_moves = metadata.human_moves();
+        let player = if human_moves == moves {
+            "human"
+        } else if human_moves > 0 {
+            "mixed"
+        } else {
+            match ui_settings.player {
+                AutoPlayer::Search => "search",
+                AutoPlayer::Policy => "policy",
+                AutoPlayer::Engine => "engine",
+            }
+        };
+        let automatic = player != "human";
+
+        let record = GameRecord {
+            finished: Local::now(),
+            score: board.score(),
+            max_tile: board.max_tile(),
+            moves,
+            duration: metadata.duration.as_secs_f64(),
+            seed: rng.seed,
+            player: player.to_string(),
+            strategy: automatic.then(|| ui_settings.strategy.name().to_string()),
+            depth: automatic.then_some(ui_settings.depth),
+        };
+        if let Err(error) = append_stats(&record) {
+            println!("Failed to save statistics: {}", error);
+        }
+        stats.0.push(record);
+    }
+}
+
+fn stats_ui(
+    mut contexts: EguiContexts,
+    stats: Res<Stats>,
+    board: Res<BoardResource>,
+    metadata: Res<GameMetadata>,
+) {
+    egui::Window::new("Statistics")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let games = &stats.0;
+            ui.label(format!("Games played: {}", games.len()));
+            if let Some(best) = games.iter().map(|game| game.score).max() {
+                let average =
+                    games.iter().map(|game| game.score as f64).sum::<f64>() / games.len() as f64;
+                ui.label(format!("Best score: {}", best));
+                ui.label(format!("Average score: {:.0}", average));
+
+                ui.separator();
+                egui::Grid::new("max_tiles").striped(true).show(ui, |ui| {
+                    ui.strong("Reached");
+                    ui.strong("Games");
+                    ui.end_row();
+
+                    let largest = games.iter().map(|game| game.max_tile).max().unwrap_or(0);
+                    let mut tile = 128;
+                    while tile <= largest {
+                        let reached = games.iter().filter(|game| game.max_tile >= tile).count();
+                        ui.label(tile.to_string());
+                        ui.label(format!(
+                            "{:.1}%",
+                            100.0 * reached as f64 / games.len() as f64
+                        ));
+                        ui.end_row();
+                        tile *= 2;
+                    }
+                });
+
+                ui.separator();
+                ui.label("Scores");
+                Plot::new("score_histogram")
+                    .height(150.0)
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .show(ui, |plot| plot.bar_chart(score_histogram(games, best)));
+            }
+
+            ui.separator();
+            ui.label("Score over moves this game");
+            let scores: PlotPoints = metadata
+                .history
+                .iter()
+                .map(|pair| pair.input.score())
+                .chain(std::iter::once(board.score()))
+                .enumerate()
+                .map(|(moves, score)| [moves as f64, score as f64])
+                .collect();
+            Plot::new("score_over_moves")
+                .height(150.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .show(ui, |plot| plot.line(Line::new(scores)));
+        });
+}
+
+/// Scores grouped into 20 bins from 0 to the best score
+fn score_histogram(games: &[GameRecord], best: u32) -> BarChart {
+    const BINS: usize = 20;
+    let width = (best as f64 / BINS as f64).max(1.0);
+
+    let mut counts = [0; BINS];
+    for game in games {
+        let bin = (game.score as f64 / width) as usize;
+        counts[bin.min(BINS - 1)] += 1;
+    }
+
+    let bars = counts
+        .iter()
+        .enumerate()
+        .map(|(bin, &count)| Bar::new((bin as f64 + 0.5) * width, count as f64).width(width))
+        .collect();
+    BarChart::new(bars)
+}
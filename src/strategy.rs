@@ -0,0 +1,264 @@
+//! The search algorithms that can drive the automatic player.
+
+use bevy::reflect::Reflect;
+use minimax::{
+    Evaluator, IterativeOptions, MCTSOptions, MonteCarloTreeSearch, Negamax, ParallelOptions,
+    ParallelSearch, Strategy as _, WORST_EVAL,
+};
+use rand::seq::SliceRandom;
+use std::time::Duration;
+
+use crate::{
+    board::{Board, Moves, Swipe},
+    evaluators::{TwentyFortyEight, TwentyFortyEightEvaluator},
+};
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum Strategy {
+    /// Plain alpha-beta search
+    Negamax,
+    /// Multithreaded iterative deepening with a transposition table
+    ParallelIterative {
+        threads: usize,
+        table_megabytes: usize,
+    },
+    /// Monte Carlo tree search with random rollouts
+    Mcts {
+        rollouts: u32,
+        max_rollout_depth: u32,
+        threads: usize,
+    },
+    /// Averages over random tile placements instead of assuming the worst
+    Expectimax {
+        /// Branches less likely than this are evaluated instead of searched
+        min_probability: f32,
+    },
+    Random,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::ParallelIterative {
+            threads: 8,
+            table_megabytes: 128,
+        }
+    }
+}
+
+impl std::str::FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "negamax" => Ok(Strategy::Negamax),
+            "parallel" => Ok(Strategy::default()),
+            "mcts" => Ok(Strategy::Mcts {
+                rollouts: 1000,
+                max_rollout_depth: 100,
+                threads: 8,
+            }),
+            "expectimax" => Ok(Strategy::Expectimax {
+                min_probability: 0.0001,
+            }),
+            "random" => Ok(Strategy::Random),
+            _ => Err(format!("Unknown strategy: {}", s)),
+        }
+    }
+}
+
+impl Strategy {
+    /// The name the strategy is parsed from
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Negamax => "negamax",
+            Strategy::ParallelIterative { .. } => "parallel",
+            Strategy::Mcts { .. } => "mcts",
+            Strategy::Expectimax { .. } => "expectimax",
+            Strategy::Random => "random",
+        }
+    }
+
+    pub fn threads(&self) -> Option<usize> {
+        match self {
+            Strategy::ParallelIterative { threads, .. } | Strategy::Mcts { threads, .. } => {
+                Some(*threads)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn table_megabytes(&self) -> Option<usize> {
+        match self {
+            Strategy::ParallelIterative {
+                table_megabytes, ..
+            } => Some(*table_megabytes),
+            _ => None,
+        }
+    }
+
+    /// Sets the thread count and table size of the strategies that use them
+    pub fn with_resources(
+        mut self,
+        threads: Option<usize>,
+        table_megabytes: Option<usize>,
+    ) -> Self {
+        match &mut self {
+            Strategy::ParallelIterative {
+                threads: strategy_threads,
+                table_megabytes: strategy_table,
+            } => {
+                *strategy_threads = threads.unwrap_or(*strategy_threads);
+                *strategy_table = table_megabytes.unwrap_or(*strategy_table);
+            }
+            Strategy::Mcts {
+                threads: strategy_threads,
+                ..
+            } => *strategy_threads = threads.unwrap_or(*strategy_threads),
+            _ => {}
+        }
+        self
+    }
+}
+
+/// A strategy along with any state it keeps between moves
+pub struct Search {
+    pub strategy: Strategy,
+    parallel: Option<ParallelSearch<TwentyFortyEightEvaluator>>,
+    timeout: Option<Duration>,
+}
+
+impl Search {
+    pub fn new(strategy: Strategy) -> Self {
+        // only the parallel search is worth keeping around, the others are
+        // cheap to create and not `Send`
+        let parallel = match strategy {
+            Strategy::ParallelIterative {
+                threads,
+                table_megabytes,
+            } => Some(ParallelSearch::new(
+                TwentyFortyEightEvaluator,
+                IterativeOptions::default()
+                    .with_table_byte_size(table_megabytes.max(1) * 1_000_000),
+                ParallelOptions::default().with_num_threads(threads.max(1)),
+            )),
+            _ => None,
+        };
+
+        Self {
+            strategy,
+            parallel,
+            timeout: None,
+        }
+    }
+
+    /// Limits the search by time instead of depth. Only the parallel search
+    /// and MCTS support this, the others always search to the given depth.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Depth is in plies and is ignored by strategies that don't use it
+    pub fn choose_move(&mut self, board: &Board, depth: u8) -> Option<Swipe> {
+        let best_move = match &self.strategy {
+            Strategy::Negamax => Negamax::new(TwentyFortyEightEvaluator, depth).choose_move(board),
+            Strategy::ParallelIterative { .. } => {
+                let parallel = self.parallel.as_mut().unwrap();
+                match self.timeout {
+                    Some(timeout) => parallel.set_timeout(timeout),
+                    None => parallel.set_max_depth(depth),
+                }
+                parallel.choose_move(board)
+            }
+            Strategy::Mcts {
+                rollouts,
+                max_rollout_depth,
+                threads,
+            } => {
+                let options = MCTSOptions::default()
+                    .with_max_rollout_depth(*max_rollout_depth)
+                    .with_num_threads((*threads).max(1));
+                let mut mcts = MonteCarloTreeSearch::<TwentyFortyEight>::new(options);
+                mcts.set_max_rollouts((*rollouts).max(1));
+                if let Some(timeout) = self.timeout {
+                    mcts.set_timeout(timeout);
+                }
+                mcts.choose_move(board)
+            }
+            Strategy::Expectimax { min_probability } => {
+                return expectimax_move(board, depth, *min_probability);
+            }
+            Strategy::Random => {
+                let moves = board.get_moves();
+                moves.choose(&mut rand::thread_rng()).copied()
+            }
+        };
+
+        match best_move? {
+            Moves::Player(swipe) => Some(swipe),
+            Moves::Computer(_) => panic!("Wrong players turn!"),
+        }
+    }
+}
+
+fn expectimax_move(board: &Board, depth: u8, min_probability: f32) -> Option<Swipe> {
+    let mut best = None;
+    let mut best_value = f32::MIN;
+    for swipe in Swipe::ALL {
+        let mut child = board.clone();
+        if !child.swipe(swipe) {
+            continue;
+        }
+
+        let value = expectimax(&child, depth.saturating_sub(1), 1.0, min_probability);
+        if value > best_value {
+            best_value = value;
+            best = Some(swipe);
+        }
+    }
+    best
+}
+
+/// The expected value of the board from the player's perspective
+fn expectimax(board: &Board, depth: u8, probability: f32, min_probability: f32) -> f32 {
+    if depth == 0 || probability < min_probability {
+        let value = TwentyFortyEightEvaluator.evaluate(board) as f32;
+        return if board.player_to_move { value } else { -value };
+    }
+
+    match board.player_to_move {
+        true => Swipe::ALL
+            .iter()
+            .filter_map(|swipe| {
+                let mut child = board.clone();
+                child
+                    .swipe(*swipe)
+                    .then(|| expectimax(&child, depth - 1, probability, min_probability))
+            })
+            .fold(WORST_EVAL as f32, f32::max),
+        false => {
+            let mut empty_tiles = Vec::new();
+            for y in 0..4 {
+                for x in 0..4 {
+                    if board.data[y][x] == 0 {
+                        empty_tiles.push((x, y));
+                    }
+                }
+            }
+
+            let tile_probability = 1.0 / empty_tiles.len() as f32;
+            let mut value = 0.0;
+            for (x, y) in empty_tiles {
+                for (exp, chance) in [(1, 0.9), (2, 0.1)] {
+                    let mut child = board.clone();
+                    child.data[y][x] = exp;
+                    child.player_to_move = true;
+
+                    let chance = chance * tile_probability;
+                    value += chance
+                        * expectimax(&child, depth - 1, probability * chance, min_probability);
+                }
+            }
+            value
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Colors for the board and its tiles. The built in themes are in
+//! `assets/themes`, and more can be added as TOML files in the `themes`
+//! folder of the config directory.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{ffi::OsStr, io, path::Path};
+
+use crate::{board::MAX_EXPONENT, config::config_dir};
+
+/// A color written as `#rrggbb`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("Invalid color {:?}, expected #rrggbb", value);
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        // also keeps the slices below on character boundaries
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl From<Rgb> for Color {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        Color::rgb_u8(r, g, b)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Rgb,
+    /// The gaps between the tiles
+    pub board: Rgb,
+    /// The score, clock and move count
+    pub text: Rgb,
+    /// Text on the tiles, light from `light_text_from` onwards
+    pub dark_text: Rgb,
+    pub light_text: Rgb,
+    pub light_text_from: u8,
+    /// Colors by exponent, starting with empty cells. Tiles past the end use
+    /// the last color.
+    pub tiles: Vec<Rgb>,
+}
+
+const BUILT_IN: [&str; 4] = [
+    include_str!("../assets/themes/classic.toml"),
+    include_str!("../assets/themes/dark.toml"),
+    include_str!("../assets/themes/high-contrast.toml"),
+    include_str!("../assets/themes/colorblind.toml"),
+];
+
+impl Theme {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let theme: Theme = toml::from_str(input).map_err(|error| error.to_string())?;
+        if theme.tiles.len() <= MAX_EXPONENT as usize {
+            return Err(format!(
+                "Theme {} needs {} tile colors, from empty cells up to {}",
+                theme.name,
+                MAX_EXPONENT + 1,
+                1u32 << MAX_EXPONENT
+            ));
+        }
+        Ok(theme)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let input = std::fs::read_to_string(path)?;
+        Self::parse(&input).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn tile(&self, exp: u8) -> Rgb {
+        let last = self.tiles.len() - 1;
+        self.tiles[(exp as usize).min(last)]
+    }
+
+    pub fn tile_text(&self, exp: u8) -> Rgb {
+        if exp < self.light_text_from {
+            self.dark_text
+        } else {
+            self.light_text
+        }
+    }
+}
+
+/// Every theme that can be picked, built in ones first. A user theme with the
+/// name of a built in one replaces it.
+#[derive(Resource, Clone)]
+pub struct Themes(pub Vec<Theme>);
+
+impl Themes {
+    pub fn load() -> Self {
+        let mut themes: Vec<Theme> = BUILT_IN
+            .iter()
+            .map(|input| Theme::parse(input).unwrap())
+            .collect();
+
+        let entries = config_dir().and_then(|dir| std::fs::read_dir(dir.join("themes")));
+        for entry in entries.into_iter().flatten() {
+            let Ok(entry) = entry else { continue };
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("toml")) {
+                continue;
+            }
+            match Theme::load(&path) {
+                Ok(theme) => match themes.iter_mut().find(|other| other.name == theme.name) {
+                    Some(other) => *other = theme,
+                    None => themes.push(theme),
+                },
+                Err(error) => println!("Skipping theme {}: {}", path.display(), error),
+            }
+        }
+
+        Self(themes)
+    }
+
+    /// The theme with the given name, or the first one if there is none
+    pub fn get(&self, name: &str) -> &Theme {
+        self.0
+            .iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(name))
+            .unwrap_or(&self.0[0])
+    }
+}
+
+/// A font size that fits the tile's number, however many digits it has, in a
+/// tile `tile_size` wide
+pub fn tile_font_size(exp: u8, tile_size: f32) -> f32 {
+    let digits = 2f64.powi(exp as i32).log10().floor() as f32 + 1.0;
+    // bold digits are a little over half as wide as they are tall
+    (tile_size * 0.45).min(tile_size * 0.85 / (0.6 * digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(
+            Rgb::try_from("#0a1B2c".to_string()),
+            Ok(Rgb(0x0a, 0x1b, 0x2c))
+        );
+        for invalid in ["0a1b2c", "#0a1b2", "#0a1b2g", "#+a1b2c", "#aé123"] {
+            assert!(Rgb::try_from(invalid.to_string()).is_err(), "{}", invalid);
+        }
+    }
+}
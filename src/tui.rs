@@ -0,0 +1,237 @@
+//! A terminal frontend for machines without a display, playing the same
+//! [`Game`] and [`Player`]s as the rest of the app.
+//!
+//! Arrows or WASD swipe, `u` undoes, space toggles autoplay, `+` and `-`
+//! change the search depth, `r` starts a new game and `q` quits.
+
+use bevy::tasks::{AsyncComputeTaskPool, Task, TaskPool};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute, queue,
+    style::{self, Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal,
+};
+use futures_lite::future;
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    board::Swipe,
+    game::Game,
+    player::{Decision, Player, SearchPlayer},
+    strategy::Strategy,
+    theme::{Rgb, Theme},
+};
+
+const TILE_WIDTH: u16 = 8;
+const TILE_HEIGHT: u16 = 3;
+
+struct Tui {
+    game: Game,
+    history: Vec<Game>,
+    search: SearchPlayer,
+    automatic: bool,
+    pending: Option<Task<Option<Swipe>>>,
+    theme: Theme,
+}
+
+impl Tui {
+    fn new_game(&mut self, seed: u64) {
+        self.game = Game::new(seed);
+        self.history.clear();
+        self.pending = None;
+    }
+
+    fn play(&mut self, swipe: Swipe) {
+        let previous = self.game.clone();
+        if self.game.play(swipe) {
+            self.history.push(previous);
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(previous) = self.history.pop() {
+            self.game = previous;
+            self.automatic = false;
+            self.pending = None;
+        }
+    }
+
+    /// Asks the search for a move, or applies the one it found
+    fn autoplay(&mut self) {
+        if let Some(task) = &mut self.pending {
+            if let Some(swipe) = future::block_on(future::poll_once(task)) {
+                self.pending = None;
+                match swipe {
+                    Some(swipe) => self.play(swipe),
+                    None => self.automatic = false,
+                }
+            }
+        } else if !self.game.is_over() {
+            match self.search.play(&self.game) {
+                Decision::Ready(Some(swipe)) => self.play(swipe),
+                Decision::Pending(task) => self.pending = Some(task),
+                Decision::Ready(None) | Decision::Waiting => self.automatic = false,
+            }
+        } else {
+            self.automatic = false;
+        }
+    }
+
+    fn draw(&self, output: &mut impl Write) -> io::Result<()> {
+        queue!(
+            output,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        for y in 0..4 {
+            for line in 0..TILE_HEIGHT {
+                queue!(output, cursor::MoveTo(0, y as u16 * TILE_HEIGHT + line))?;
+                for x in 0..4 {
+                    let exp = self.game.data[y][x];
+                    let text = if line == TILE_HEIGHT / 2 && exp > 0 {
+                        (1u32 << exp).to_string()
+                    } else {
+                        String::new()
+                    };
+                    queue!(
+                        output,
+                        SetBackgroundColor(terminal_color(self.theme.tile(exp))),
+                        SetForegroundColor(terminal_color(self.theme.tile_text(exp))),
+                        Print(format!("{:^width$}", text, width = TILE_WIDTH as usize)),
+                    )?;
+                }
+                queue!(output, ResetColor)?;
+            }
+        }
+
+        let status_line = 4 * TILE_HEIGHT + 1;
+        queue!(
+            output,
+            cursor::MoveTo(0, status_line),
+            Print(format!(
+                "Score: {}  Best tile: {}  Moves: {}",
+                self.game.score(),
+                self.game.max_tile(),
+                self.game.moves
+            )),
+            cursor::MoveTo(0, status_line + 1),
+            Print(format!(
+                "{:?} depth {}  Autoplay: {}",
+                self.search.strategy,
+                self.search.depth,
+                if self.automatic { "on" } else { "off" }
+            )),
+            cursor::MoveTo(0, status_line + 2),
+        )?;
+        if self.game.is_over() {
+            queue!(
+                output,
+                style::SetAttribute(style::Attribute::Bold),
+                Print("Game over! Press r for a new game or u to undo"),
+                style::SetAttribute(style::Attribute::Reset),
+            )?;
+        } else {
+            queue!(
+                output,
+                Print("arrows/wasd swipe, u undo, space autoplay, +/- depth, r restart, q quit")
+            )?;
+        }
+
+        output.flush()
+    }
+}
+
+fn terminal_color(Rgb(r, g, b): Rgb) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+/// Puts the terminal back the way it was, even if the game panics
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the terminal frontend until the player quits
+pub fn run(strategy: Strategy, depth: u8, seed: u64, theme: Theme) -> io::Result<()> {
+    AsyncComputeTaskPool::init(TaskPool::default);
+    let mut tui = Tui {
+        game: Game::new(seed),
+        history: Vec::new(),
+        search: SearchPlayer::new(strategy, depth),
+        automatic: false,
+        pending: None,
+        theme,
+    };
+
+    let _terminal = RawTerminal::enter()?;
+    let mut stdout = io::stdout();
+    tui.draw(&mut stdout)?;
+
+    loop {
+        let mut changed = false;
+
+        if event::poll(Duration::from_millis(20))? {
+            if let Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            {
+                let swipe = match code {
+                    KeyCode::Up | KeyCode::Char('w') => Some(Swipe::Up),
+                    KeyCode::Down | KeyCode::Char('s') => Some(Swipe::Down),
+                    KeyCode::Left | KeyCode::Char('a') => Some(Swipe::Left),
+                    KeyCode::Right | KeyCode::Char('d') => Some(Swipe::Right),
+                    _ => None,
+                };
+
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('u') => tui.undo(),
+                    KeyCode::Char('r') => tui.new_game(rand::random()),
+                    KeyCode::Char(' ') => tui.automatic = !tui.automatic,
+                    KeyCode::Char('+') | KeyCode::Char('=') => {
+                        tui.search.depth = tui.search.depth.saturating_add(1)
+                    }
+                    KeyCode::Char('-') => tui.search.depth = tui.search.depth.max(2) - 1,
+                    _ => {}
+                }
+                if let Some(swipe) = swipe {
+                    if tui.pending.is_none() {
+                        tui.play(swipe);
+                    }
+                }
+                changed = true;
+            }
+        }
+
+        if tui.automatic {
+            let moves = tui.game.moves;
+            tui.autoplay();
+            changed |= tui.game.moves != moves || !tui.automatic;
+        }
+
+        if changed {
+            tui.draw(&mut stdout)?;
+        }
+    }
+
+    Ok(())
+}
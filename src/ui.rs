@@ -1,7 +1,22 @@
 use crate::{
-    board::Board,
-    record::{load_board_from_file, RecordEvent, RecordInfo},
-    BoardResource, render::UpdateBoardEvent,
+    analysis::{
+        annotations_path, load_annotations, review, save_annotations, Annotation, AnnotationKind,
+        ReviewOptions,
+    },
+    board::{Board, Swipe},
+    config::config_dir,
+    notation::Notation,
+    player::{EngineResource, ExternalPlayer},
+    policy::{Policy, PolicyResource},
+    record::{
+        load_recording, serialize_recording, ForkOrigin, GameMetadata, InoutPair, RecordEvent,
+        RecordInfo,
+    },
+    render::UpdateBoardEvent,
+    slots::{list_slots, slot_path, SavedGame, Slot},
+    strategy::Strategy,
+    theme::Themes,
+    BoardResource, GameCommand, GameMode, GameRng,
 };
 use bevy::prelude::*;
 use bevy_inspector_egui::{
@@ -10,6 +25,7 @@ use bevy_inspector_egui::{
     reflect_inspector::ui_for_value,
     DefaultInspectorConfigPlugin,
 };
+use chrono::Local;
 use futures_lite::future;
 use std::path::PathBuf;
 
@@ -21,107 +37,618 @@ impl Plugin for UIPlugin {
             .insert_resource(UIState {
                 board_selector: 0,
                 loaded_recording: None,
+                loaded_path: None,
+                annotations: Vec::new(),
             })
+            .init_resource::<SlotState>()
+            .init_resource::<SidePanelWidth>()
             .init_resource::<UiSettings>()
             .register_type::<UiSettings>()
-            .add_systems(Update, ui_system);
+            .register_type::<AutoPlayer>()
+            .register_type::<Strategy>()
+            .add_systems(
+                Update,
+                (
+                    ui_system,
+                    measure_side_panel.after(ui_system),
+                    slots_ui,
+                    settings_commands,
+                    load_policy,
+                    load_engine,
+                ),
+            );
     }
 }
 
 #[derive(Resource, Reflect)]
 pub struct UiSettings {
+    pub player: AutoPlayer,
+    pub strategy: Strategy,
     pub depth: u8,
     pub automatic: bool,
     pub speed: f32,
+    /// Start where the last session left off
+    pub resume_last_game: bool,
+    /// How far in pixels a drag across the board goes before it's a swipe
+    pub swipe_distance: f32,
+    /// How many times longer than it is wide a drag has to be, so diagonal
+    /// drags are ignored
+    pub swipe_ratio: f32,
+    // picked from the loaded themes rather than typed in
+    #[reflect(ignore)]
+    pub theme: String,
 }
 
 impl Default for UiSettings {
     fn default() -> Self {
         Self {
+            player: AutoPlayer::Search,
+            strategy: Strategy::default(),
             depth: 8,
             automatic: false,
             speed: 0.0,
+            resume_last_game: false,
+            swipe_distance: 30.0,
+            swipe_ratio: 1.5,
+            theme: "Classic".to_string(),
         }
     }
 }
 
+/// Who makes the moves when `automatic` is on
+#[derive(Reflect, Clone, Copy, PartialEq, Eq)]
+pub enum AutoPlayer {
+    Search,
+    Policy,
+    Engine,
+}
+
+/// How much of the right of the window the settings panel covers, in
+/// logical pixels
+#[derive(Resource, Default)]
+pub struct SidePanelWidth(pub f32);
+
 #[derive(Resource)]
 struct UIState {
     board_selector: usize,
-    loaded_recording: Option<Vec<u8>>,
+    loaded_recording: Option<Vec<InoutPair<Board, Swipe>>>,
+    loaded_path: Option<PathBuf>,
+    annotations: Vec<Annotation>,
 }
 
+impl UIState {
+    /// Metadata for a game continuing from the selected recording position
+    fn fork_metadata(&self) -> Option<GameMetadata> {
+        let recording = self.loaded_recording.as_ref()?;
+        Some(GameMetadata {
+            forked_from: Some(ForkOrigin {
+                file: self.loaded_path.clone()?,
+                index: self.board_selector,
+            }),
+            history: recording[..self.board_selector].to_vec(),
+            human: vec![false; self.board_selector],
+            ..default()
+        })
+    }
+}
+
+#[derive(Resource, Default)]
+struct SlotState {
+    /// The name typed in for a new slot
+    name: String,
+    /// Read from disk when the window first opens and after every change
+    slots: Option<Vec<Slot>>,
+}
+
+enum SlotAction {
+    Save(String),
+    Load(usize),
+    Delete(usize),
+}
+
+#[allow(clippy::too_many_arguments)]
 fn ui_system(
     mut commands: Commands,
     mut contexts: EguiContexts,
     event_info: Res<RecordInfo>,
-    mut record_event: EventWriter<RecordEvent>,
     mut ui_state: ResMut<UIState>,
     mut ui_settings: ResMut<UiSettings>,
     mut board: ResMut<BoardResource>,
+    mut metadata: ResMut<GameMetadata>,
     mut events: EventWriter<UpdateBoardEvent>,
+    mut game_commands: EventWriter<GameCommand>,
     mut file_dialog: Query<(Entity, &mut SelectedFile)>,
+    themes: Res<Themes>,
+    mut review_tasks: Query<(Entity, &mut ReviewTask)>,
+    mut mode: ResMut<GameMode>,
     type_registry: Res<AppTypeRegistry>,
 ) {
-    egui::Window::new("Settings").show(contexts.ctx_mut(), |ui| {
-        ui_for_value(ui_settings.as_mut(), ui, &type_registry.read());
-
-        if ui.button("Reset board").clicked() {
-            let mut new_board = Board::new();
-            new_board.add_random();
-            new_board.add_random();
-            board.0 = new_board;
+    // a side panel, so the settings never cover the board
+    egui::SidePanel::right("settings").show(contexts.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui_for_value(ui_settings.as_mut(), ui, &type_registry.read());
+
+            egui::ComboBox::from_label("Theme")
+                .selected_text(themes.get(&ui_settings.theme).name.clone())
+                .show_ui(ui, |ui| {
+                    for theme in themes.0.iter() {
+                        let selected = ui.selectable_value(
+                            &mut ui_settings.theme,
+                            theme.name.clone(),
+                            &theme.name,
+                        );
+                        if selected.changed() {
+                            events.send(UpdateBoardEvent);
+                        }
+                    }
+                });
+
+            if ui.button("Reset board").clicked() {
+                game_commands.send(GameCommand::Reset { seed: None });
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Copy position").clicked() {
+                    game_commands.send(GameCommand::CopyPosition);
+                }
+
+                if ui.button("Paste position").clicked() {
+                    game_commands.send(GameCommand::PastePosition);
+                }
+            });
+
+            if *mode != GameMode::Edit && ui.button("Edit position").clicked() {
+                game_commands.send(GameCommand::EditPosition);
+            }
+
+            let recording = match event_info.recording {
+                true => "Stop Recording",
+                false => "Start Recording",
+            };
+            if ui.button(recording).clicked() {
+                game_commands.send(GameCommand::ToggleRecording);
+            }
+
+            if ui.button("Load file").clicked() {
+                game_commands.send(GameCommand::LoadRecording);
+            }
+
+            if ui.button("Load policy").clicked() {
+                let thread_pool = bevy::tasks::AsyncComputeTaskPool::get();
+                let task = thread_pool.spawn(async move {
+                    rfd::FileDialog::new()
+                        .add_filter("2048 policy", &["tfp"])
+                        .pick_file()
+                });
+                commands.spawn(SelectedPolicy(task));
+            }
+
+            if ui.button("Load engine").clicked() {
+                let depth = ui_settings.depth;
+                let thread_pool = bevy::tasks::AsyncComputeTaskPool::get();
+                let task = thread_pool.spawn(async move {
+                    let path = rfd::FileDialog::new().pick_file()?;
+                    Some(ExternalPlayer::spawn(path, &[], &[], depth, None))
+                });
+                commands.spawn(SelectedEngine(task));
+            }
+
+            let last_index = ui_state
+                .loaded_recording
+                .as_ref()
+                .and_then(|recording| recording.len().checked_sub(1));
+            if let Some(recording_length) = last_index {
+                let slider = egui::Slider::new(&mut ui_state.board_selector, 0..=recording_length)
+                    .text("Board index");
+
+                if ui.add(slider).changed() {
+                    let recording = ui_state.loaded_recording.as_ref().unwrap();
+                    board.0 = recording[ui_state.board_selector].input.clone();
+                    *mode = GameMode::Replay;
+                    events.send(UpdateBoardEvent);
+                }
+
+                // annotation markers lined up under the slider
+                if !ui_state.annotations.is_empty() {
+                    let size = egui::vec2(ui.spacing().slider_width, 6.0);
+                    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                    for annotation in ui_state.annotations.iter() {
+                        let t = annotation.index as f32 / recording_length.max(1) as f32;
+                        let x = rect.left() + t * rect.width();
+                        ui.painter().line_segment(
+                            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                            (2.0, annotation_color(annotation.kind)),
+                        );
+                    }
+
+                    for annotation in ui_state.annotations.iter() {
+                        if annotation.index == ui_state.board_selector {
+                            let text = match annotation.kind {
+                                AnnotationKind::Blunder => {
+                                    format!("Blunder (-{})", annotation.loss)
+                                }
+                                AnnotationKind::Critical => "Critical position".to_string(),
+                            };
+                            ui.colored_label(annotation_color(annotation.kind), text);
+                        }
+                    }
+                }
+
+                if !review_tasks.is_empty() {
+                    ui.label("Reviewing...");
+                } else if ui.button("Review").clicked() {
+                    if let Some(path) = ui_state.loaded_path.clone() {
+                        let recording = ui_state.loaded_recording.clone().unwrap();
+                        commands.spawn(ReviewTask::spawn(recording, path, &ui_settings));
+                    }
+                }
+
+                if ui.button("Play from here").clicked() {
+                    let recording = ui_state.loaded_recording.as_ref().unwrap();
+                    board.0 = recording[ui_state.board_selector].input.clone();
+                    *metadata = ui_state.fork_metadata().unwrap();
+                    *mode = GameMode::Play;
+                    events.send(UpdateBoardEvent);
+                }
+
+                if ui.button("Close recording").clicked() {
+                    ui_state.loaded_recording = None;
+                    ui_state.loaded_path = None;
+                    ui_state.annotations.clear();
+                    if *mode == GameMode::Replay {
+                        *mode = GameMode::Play;
+                    }
+                }
+            }
+
+            // a finished game is saved as a recording so the review has
+            // somewhere to be stored
+            if ui_state.loaded_recording.is_none()
+                && metadata.finished
+                && !metadata.history.is_empty()
+                && review_tasks.is_empty()
+                && ui.button("Review game").clicked()
+            {
+                match save_for_review(&metadata.history) {
+                    Ok(path) => {
+                        let recording = metadata.history.clone();
+                        commands.spawn(ReviewTask::spawn(
+                            recording.clone(),
+                            path.clone(),
+                            &ui_settings,
+                        ));
+                        ui_state.board_selector = 0;
+                        ui_state.loaded_recording = Some(recording);
+                        ui_state.loaded_path = Some(path);
+                        ui_state.annotations.clear();
+                    }
+                    Err(error) => println!("Failed to save game for review: {}", error),
+                }
+            }
+        });
+    });
+
+    // check for file dialog completion
+    for (entity, mut selected_file) in file_dialog.iter_mut() {
+        if let Some(result) = future::block_on(future::poll_once(&mut selected_file.0)) {
+            commands.entity(entity).despawn();
+            let Some(path) = result else {
+                continue;
+            };
+
+            let recording = match std::fs::read(&path).and_then(|file| load_recording(&file)) {
+                Ok(recording) if recording.is_empty() => {
+                    println!("Failed to load recording: {} has no moves", path.display());
+                    continue;
+                }
+                Ok(recording) => recording,
+                Err(error) => {
+                    println!("Failed to load recording: {}", error);
+                    continue;
+                }
+            };
+            board.0 = recording[0].input.clone();
             events.send(UpdateBoardEvent);
+            *mode = GameMode::Replay;
+
+            ui_state.board_selector = 0;
+            ui_state.loaded_recording = Some(recording);
+            ui_state.annotations = load_annotations(&annotations_path(&path)).unwrap_or_default();
+            ui_state.loaded_path = Some(path);
+        }
+    }
+
+    for (entity, mut review_task) in review_tasks.iter_mut() {
+        if let Some(annotations) = future::block_on(future::poll_once(&mut review_task.task)) {
+            commands.entity(entity).despawn();
+
+            let path = annotations_path(&review_task.path);
+            if let Err(error) = save_annotations(&path, &annotations) {
+                println!("Failed to save review: {}", error);
+            }
+            // the recording may have been closed or replaced in the meantime
+            if ui_state.loaded_path.as_ref() == Some(&review_task.path) {
+                ui_state.annotations = annotations;
+            }
         }
+    }
+}
 
-        match event_info.recording {
-            true => {
-                if ui.button("Stop Recording").clicked() {
-                    record_event.send(RecordEvent::Stop);
+/// Carries out the commands for the settings window's actions, whether they
+/// came from its buttons or from a binding
+#[allow(clippy::too_many_arguments)]
+fn settings_commands(
+    mut commands: Commands,
+    mut game_commands: EventReader<GameCommand>,
+    record_info: Res<RecordInfo>,
+    mut record_event: EventWriter<RecordEvent>,
+    mut ui_state: ResMut<UIState>,
+    mut board: ResMut<BoardResource>,
+    mut metadata: ResMut<GameMetadata>,
+    mut mode: ResMut<GameMode>,
+    mut events: EventWriter<UpdateBoardEvent>,
+    file_dialog: Query<(), With<SelectedFile>>,
+) {
+    // a second dialog would open on top of the first
+    let mut dialog_open = !file_dialog.is_empty();
+    for command in game_commands.iter() {
+        match *command {
+            GameCommand::StepReplay(step) => {
+                let Some(recording) = ui_state.loaded_recording.as_ref() else {
+                    continue;
+                };
+                let Some(recording_length) = recording.len().checked_sub(1) else {
+                    continue;
+                };
+                let selector = ui_state
+                    .board_selector
+                    .saturating_add_signed(step)
+                    .min(recording_length);
+                board.0 = recording[selector].input.clone();
+                ui_state.board_selector = selector;
+                *mode = GameMode::Replay;
+                events.send(UpdateBoardEvent);
+            }
+            GameCommand::ToggleRecording => match record_info.recording {
+                true => record_event.send(RecordEvent::Stop),
+                false => record_event.send(RecordEvent::Start),
+            },
+            GameCommand::LoadRecording if !dialog_open => {
+                dialog_open = true;
+                let thread_pool = bevy::tasks::AsyncComputeTaskPool::get();
+                let task = thread_pool.spawn(async move {
+                    rfd::FileDialog::new()
+                        .add_filter("2048 recording", &["tfer"])
+                        .pick_file()
+                });
+                commands.spawn(SelectedFile(task));
+            }
+            GameCommand::CopyPosition => {
+                if let Err(error) = copy_position(&board) {
+                    println!("Failed to copy position: {}", error);
                 }
             }
-            false => {
-                if ui.button("Start Recording").clicked() {
-                    record_event.send(RecordEvent::Start);
+            GameCommand::PastePosition => match paste_position() {
+                Ok(new_board) => {
+                    board.0 = new_board;
+                    *metadata = GameMetadata::default();
+                    if *mode == GameMode::Replay {
+                        *mode = GameMode::Play;
+                    }
+                    events.send(UpdateBoardEvent);
                 }
+                Err(error) => println!("Failed to paste position: {}", error),
+            },
+            GameCommand::EditPosition if *mode != GameMode::Edit => {
+                *metadata = GameMetadata::default();
+                *mode = GameMode::Edit;
             }
+            _ => {}
         }
+    }
+}
 
-        if ui.button("Load file").clicked() {
-            let thread_pool = bevy::tasks::AsyncComputeTaskPool::get();
-            let task = thread_pool.spawn(async move {
-                rfd::FileDialog::new()
-                    .add_filter("2048 recording", &["tfer"])
-                    .pick_file()
-            });
-            commands.spawn(SelectedFile(task));
-        }
+fn measure_side_panel(mut contexts: EguiContexts, mut width: ResMut<SidePanelWidth>) {
+    let ctx = contexts.ctx_mut();
+    let covered = ctx.screen_rect().right() - ctx.available_rect().right();
+    if width.0 != covered {
+        width.0 = covered;
+    }
+}
 
-        if ui_state.loaded_recording != None {
-            let recording_length = ui_state.loaded_recording.as_ref().unwrap().len() / 17 - 1;
-            let slider = egui::Slider::new(&mut ui_state.board_selector, 0..=recording_length)
-                .text("Board index");
+fn load_policy(
+    mut commands: Commands,
+    mut policy_dialog: Query<(Entity, &mut SelectedPolicy)>,
+    mut policy: ResMut<PolicyResource>,
+) {
+    for (entity, mut selected_policy) in policy_dialog.iter_mut() {
+        if let Some(result) = future::block_on(future::poll_once(&mut selected_policy.0)) {
+            commands.entity(entity).despawn();
 
-            if ui.add(slider).changed() {
-                let file = ui_state.loaded_recording.as_ref().unwrap();
-                board.0 = load_board_from_file(file, ui_state.board_selector).input;
-                events.send(UpdateBoardEvent);
+            if let Some(path) = result {
+                match Policy::load(&path) {
+                    Ok(loaded) => policy.0 = Some(loaded),
+                    Err(error) => println!("Failed to load policy: {}", error),
+                }
             }
         }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn slots_ui(
+    mut contexts: EguiContexts,
+    mut slot_state: ResMut<SlotState>,
+    ui_state: Res<UIState>,
+    mut board: ResMut<BoardResource>,
+    mut rng: ResMut<GameRng>,
+    mut metadata: ResMut<GameMetadata>,
+    mut mode: ResMut<GameMode>,
+    mut events: EventWriter<UpdateBoardEvent>,
+) {
+    let SlotState { name, slots } = slot_state.as_mut();
+    let list = slots.get_or_insert_with(|| {
+        list_slots().unwrap_or_else(|error| {
+            println!("Failed to read save slots: {}", error);
+            Vec::new()
+        })
     });
 
-    // check for file dialog completion
-    for (entity, mut selected_file) in file_dialog.iter_mut() {
-        if let Some(result) = future::block_on(future::poll_once(&mut selected_file.0)) {
-            let path = result.unwrap();
+    let mut action = None;
+    egui::Window::new("Save slots")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("slots").striped(true).show(ui, |ui| {
+                ui.strong("Name");
+                ui.strong("Score");
+                ui.strong("Max tile");
+                ui.strong("Saved");
+                ui.end_row();
+
+                for (index, slot) in list.iter().enumerate() {
+                    ui.label(&slot.name);
+                    ui.label(slot.game.board.score().to_string());
+                    ui.label(slot.game.board.max_tile().to_string());
+                    ui.label(slot.game.saved.format("%Y-%m-%d %H:%M").to_string());
+                    if ui.button("Load").clicked() {
+                        action = Some(SlotAction::Load(index));
+                    }
+                    if ui.button("Overwrite").clicked() {
+                        action = Some(SlotAction::Save(slot.name.clone()));
+                    }
+                    if ui.button("Delete").clicked() {
+                        action = Some(SlotAction::Delete(index));
+                    }
+                    ui.end_row();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(name);
+                if ui.button("Save").clicked() {
+                    action = Some(SlotAction::Save(name.trim().to_string()));
+                }
+            });
+        });
+
+    let result = match action {
+        Some(SlotAction::Save(slot_name)) => {
+            // a position being replayed is saved as a game continuing from it
+            let game_metadata = match *mode {
+                GameMode::Replay => ui_state.fork_metadata().unwrap_or_default(),
+                GameMode::Edit => GameMetadata::default(),
+                GameMode::Play => metadata.clone(),
+            };
+            let game = SavedGame::new(board.0.clone(), *rng, game_metadata);
+            slot_path(&slot_name).and_then(|path| game.save(&path))
+        }
+        Some(SlotAction::Load(index)) => {
+            let game = list[index].game.clone();
+            board.0 = game.board;
+            *rng = game.rng;
+            *metadata = game.metadata;
+            *mode = GameMode::Play;
+            events.send(UpdateBoardEvent);
+            Ok(())
+        }
+        Some(SlotAction::Delete(index)) => {
+            slot_path(&list[index].name).and_then(|path| SavedGame::delete(&path))
+        }
+        None => return,
+    };
+
+    if let Err(error) = result {
+        println!("Save slot failed: {}", error);
+    }
+    *slots = None;
+}
+
+// launching an engine waits for its handshake, so it happens in the dialog task
+fn load_engine(
+    mut commands: Commands,
+    mut engine_dialog: Query<(Entity, &mut SelectedEngine)>,
+    mut engine: ResMut<EngineResource>,
+) {
+    for (entity, mut selected_engine) in engine_dialog.iter_mut() {
+        if let Some(result) = future::block_on(future::poll_once(&mut selected_engine.0)) {
             commands.entity(entity).despawn();
 
-            let file = std::fs::read(path).unwrap();
-            ui_state.loaded_recording = Some(file);
+            match result {
+                Some(Ok(loaded)) => engine.0 = Some(loaded),
+                Some(Err(error)) => println!("Failed to load engine: {}", error),
+                None => {}
+            }
         }
     }
 }
 
+fn copy_position(board: &Board) -> Result<(), arboard::Error> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    clipboard.set_text(Notation(board.clone()).to_string())
+}
+
+fn paste_position() -> Result<Board, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let text = clipboard.get_text().map_err(|e| e.to_string())?;
+    let Notation(board) = text.parse()?;
+    Ok(board)
+}
+
 // file loading stuff
 #[derive(Component)]
 struct SelectedFile(bevy::tasks::Task<Option<PathBuf>>);
+
+#[derive(Component)]
+struct SelectedPolicy(bevy::tasks::Task<Option<PathBuf>>);
+
+#[derive(Component)]
+struct SelectedEngine(bevy::tasks::Task<Option<std::io::Result<ExternalPlayer>>>);
+
+#[derive(Component)]
+struct ReviewTask {
+    /// The recording being reviewed, which the annotations are saved next to
+    path: PathBuf,
+    task: bevy::tasks::Task<Vec<Annotation>>,
+}
+
+impl ReviewTask {
+    fn spawn(
+        recording: Vec<InoutPair<Board, Swipe>>,
+        path: PathBuf,
+        settings: &UiSettings,
+    ) -> Self {
+        let mut options = ReviewOptions {
+            depth: settings.depth,
+            ..default()
+        };
+        if let Strategy::ParallelIterative {
+            threads,
+            table_megabytes,
+        } = settings.strategy
+        {
+            options.threads = threads;
+            options.table_megabytes = table_megabytes;
+        }
+
+        let thread_pool = bevy::tasks::AsyncComputeTaskPool::get();
+        let task = thread_pool.spawn(async move { review(&recording, &options) });
+        Self { path, task }
+    }
+}
+
+/// Writes a finished game to the reviews directory, named by when it was saved
+fn save_for_review(history: &[InoutPair<Board, Swipe>]) -> std::io::Result<PathBuf> {
+    let dir = config_dir()?.join("reviews");
+    std::fs::create_dir_all(&dir)?;
+    let name = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let path = dir.join(name).with_extension("tfer");
+    std::fs::write(&path, serialize_recording(history))?;
+    Ok(path)
+}
+
+fn annotation_color(kind: AnnotationKind) -> egui::Color32 {
+    match kind {
+        AnnotationKind::Blunder => egui::Color32::RED,
+        AnnotationKind::Critical => egui::Color32::GOLD,
+    }
+}